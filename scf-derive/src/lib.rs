@@ -0,0 +1,310 @@
+//! Derive macro for `scf::FromScf`. See the documentation of `scf::decode`.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+	parse_macro_input, parse_quote, Data, DeriveInput, Error, Fields, GenericParam, Ident, LitStr,
+	Path, Type,
+};
+
+#[proc_macro_derive(FromScf, attributes(scf))]
+pub fn derive_from_scf(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	expand(input)
+		.unwrap_or_else(Error::into_compile_error)
+		.into()
+}
+
+/// The options in `#[scf(...)]` attributes.
+#[derive(Default)]
+struct Attrs {
+	rename: Option<String>,
+	default: bool,
+	flatten: bool,
+}
+
+impl Attrs {
+	fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+		let mut a = Self::default();
+		for attr in attrs.iter().filter(|a| a.path().is_ident("scf")) {
+			attr.parse_nested_meta(|meta| {
+				if meta.path.is_ident("rename") {
+					a.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+				} else if meta.path.is_ident("default") {
+					a.default = true;
+				} else if meta.path.is_ident("flatten") {
+					a.flatten = true;
+				} else {
+					return Err(meta.error("unknown scf attribute"));
+				}
+				Ok(())
+			})?;
+		}
+		Ok(a)
+	}
+}
+
+/// A field of a struct or struct variant.
+struct Field {
+	ident: Ident,
+	ty: Type,
+	name: String,
+	attrs: Attrs,
+}
+
+fn named_fields(fields: &Fields, allow_flatten: bool) -> syn::Result<Vec<Field>> {
+	let mut v = Vec::new();
+	for f in fields {
+		let attrs = Attrs::parse(&f.attrs)?;
+		let ident = f.ident.clone().expect("named field");
+		if attrs.flatten && !allow_flatten {
+			return Err(Error::new_spanned(
+				f,
+				"flatten is only supported in structs",
+			));
+		}
+		if attrs.flatten && (attrs.default || attrs.rename.is_some()) {
+			return Err(Error::new_spanned(
+				f,
+				"flatten can't be combined with other attributes",
+			));
+		}
+		let name = attrs.rename.clone().unwrap_or_else(|| ident.to_string());
+		v.push(Field {
+			ident,
+			ty: f.ty.clone(),
+			name,
+			attrs,
+		});
+	}
+	Ok(v)
+}
+
+fn no_attrs(fields: &Fields) -> syn::Result<()> {
+	for f in fields {
+		let a = Attrs::parse(&f.attrs)?;
+		if a.rename.is_some() || a.default || a.flatten {
+			return Err(Error::new_spanned(
+				f,
+				"attributes are only supported on named fields",
+			));
+		}
+	}
+	Ok(())
+}
+
+/// Decode named fields from the `(field value)` groups in `it` and construct `path`.
+fn decode_fields(path: &Path, fields: &[Field]) -> TokenStream {
+	let slot = |i| format_ident!("__f{}", i);
+	let decls = fields
+		.iter()
+		.enumerate()
+		.filter(|(_, f)| !f.attrs.flatten)
+		.map(|(i, f)| {
+			let (slot, ty) = (slot(i), &f.ty);
+			quote!(let mut #slot: ::core::option::Option<#ty> = ::core::option::Option::None;)
+		});
+	let arms = fields
+		.iter()
+		.enumerate()
+		.filter(|(_, f)| !f.attrs.flatten)
+		.map(|(i, f)| {
+			let (slot, name) = (slot(i), &f.name);
+			quote!(#name => ::scf::decode::__private::set(&mut #slot, #name, __head, &mut __g)?,)
+		});
+	let flatten = fields
+		.iter()
+		.enumerate()
+		.filter(|(_, f)| f.attrs.flatten)
+		.map(|(i, f)| {
+			let (slot, ty, name) = (slot(i), &f.ty, f.ident.to_string());
+			quote!(let #slot = ::scf::decode::__private::flatten::<#ty>(#name, it)?;)
+		});
+	let inits = fields.iter().enumerate().map(|(i, f)| {
+		let (slot, ident, name) = (slot(i), &f.ident, &f.name);
+		if f.attrs.flatten {
+			quote!(#ident: #slot)
+		} else if f.attrs.default {
+			quote!(#ident: #slot.unwrap_or_default())
+		} else {
+			quote!(#ident: ::scf::decode::__private::required(#slot, #name, it)?)
+		}
+	});
+	quote! {
+		#(#decls)*
+		while let ::core::option::Option::Some(__item) = ::core::iter::Iterator::next(it) {
+			let (__head, mut __g) = ::scf::decode::__private::field(it, __item)?;
+			match __head.text {
+				#(#arms)*
+				_ => {}
+			}
+		}
+		::scf::decode::check(it)?;
+		#(#flatten)*
+		::core::result::Result::Ok(#path { #(#inits),* })
+	}
+}
+
+/// Decode unnamed fields from one item each and construct `path`.
+fn decode_tuple(path: &Path, fields: &Fields) -> TokenStream {
+	let items = fields.iter().map(|_| quote!(::scf::decode::next(it)?));
+	quote! {
+		let __v = #path(#(#items),*);
+		::scf::decode::end(it)?;
+		::core::result::Result::Ok(__v)
+	}
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+	let ident = &input.ident;
+	let (from_items, from_item) = match &input.data {
+		Data::Struct(s) => {
+			let path = parse_quote!(Self);
+			match &s.fields {
+				Fields::Named(_) => (decode_fields(&path, &named_fields(&s.fields, true)?), None),
+				Fields::Unnamed(f) if f.unnamed.len() == 1 => {
+					no_attrs(&s.fields)?;
+					let ty = &f.unnamed[0].ty;
+					let items = quote! {
+						<#ty as ::scf::FromScf<'scf>>::from_items(it).map(Self)
+					};
+					let item = quote! {
+						<#ty as ::scf::FromScf<'scf>>::from_item(__item).map(Self)
+					};
+					(items, Some(item))
+				}
+				Fields::Unnamed(_) => {
+					no_attrs(&s.fields)?;
+					(decode_tuple(&path, &s.fields), None)
+				}
+				Fields::Unit => (quote!(::scf::decode::end(it).map(|()| Self)), None),
+			}
+		}
+		Data::Enum(e) => {
+			let (mut arms, mut unit_arms) = (Vec::new(), Vec::new());
+			for v in &e.variants {
+				let attrs = Attrs::parse(&v.attrs)?;
+				if attrs.default || attrs.flatten {
+					return Err(Error::new_spanned(
+						v,
+						"only rename is supported on variants",
+					));
+				}
+				let name = attrs.rename.unwrap_or_else(|| v.ident.to_string());
+				let vi = &v.ident;
+				let path: Path = parse_quote!(Self::#vi);
+				let body = match &v.fields {
+					Fields::Named(_) => decode_fields(&path, &named_fields(&v.fields, false)?),
+					Fields::Unnamed(f) if f.unnamed.len() == 1 => {
+						no_attrs(&v.fields)?;
+						let ty = &f.unnamed[0].ty;
+						quote!(<#ty as ::scf::FromScf<'scf>>::from_items(it).map(#path))
+					}
+					Fields::Unnamed(_) => {
+						no_attrs(&v.fields)?;
+						decode_tuple(&path, &v.fields)
+					}
+					Fields::Unit => quote!(::scf::decode::end(it).map(|()| #path)),
+				};
+				arms.push(quote!(#name => { #body }));
+				unit_arms.push(match v.fields {
+					Fields::Unit => quote!(#name => ::core::result::Result::Ok(#path),),
+					_ => quote! {
+						#name => ::core::result::Result::Err(
+							::scf::DecodeError::expected("a group", __a.span),
+						),
+					},
+				});
+			}
+			let items = quote! {
+				let __head = match ::scf::decode::__private::variant(it)? {
+					::scf::Item::Str(__a) => __a,
+					__item => {
+						let __v = <Self as ::scf::FromScf<'scf>>::from_item(__item)
+							.map_err(|e| e.locate(it.data()))?;
+						::scf::decode::end(it)?;
+						return ::core::result::Result::Ok(__v);
+					}
+				};
+				match __head.text {
+					#(#arms)*
+					_ => ::core::result::Result::Err(
+						::scf::decode::__private::unknown_variant(__head).locate(it.data()),
+					),
+				}
+			};
+			let item = quote! {
+				match __item {
+					::scf::Item::Group(mut __g) => {
+						<Self as ::scf::FromScf<'scf>>::from_items(&mut __g)
+					}
+					::scf::Item::Str(__a) => match __a.text {
+						#(#unit_arms)*
+						_ => ::core::result::Result::Err(
+							::scf::decode::__private::unknown_variant(__a),
+						),
+					},
+				}
+			};
+			(items, Some(item))
+		}
+		Data::Union(u) => {
+			return Err(Error::new_spanned(
+				u.union_token,
+				"unions are not supported",
+			));
+		}
+	};
+
+	let mut generics = input.generics.clone();
+	let lifetimes = input
+		.generics
+		.lifetimes()
+		.map(|l| l.lifetime.clone())
+		.collect::<Vec<_>>();
+	generics.params.insert(
+		0,
+		GenericParam::Lifetime(syn::LifetimeParam {
+			attrs: Vec::new(),
+			lifetime: syn::Lifetime::new("'scf", Span::call_site()),
+			colon_token: None,
+			bounds: lifetimes.into_iter().collect(),
+		}),
+	);
+	if let Some(GenericParam::Lifetime(l)) = generics.params.first_mut() {
+		if !l.bounds.is_empty() {
+			l.colon_token = Some(Default::default());
+		}
+	}
+	let where_clause = generics.make_where_clause();
+	for t in input.generics.type_params() {
+		let t = &t.ident;
+		where_clause
+			.predicates
+			.push(parse_quote!(#t: ::scf::FromScf<'scf>));
+	}
+	let (impl_generics, _, where_clause) = generics.split_for_impl();
+	let (_, ty_generics, _) = input.generics.split_for_impl();
+
+	let from_item = from_item.map(|body| {
+		quote! {
+			fn from_item(
+				__item: ::scf::Item<'scf, '_>,
+			) -> ::core::result::Result<Self, ::scf::DecodeError> {
+				#body
+			}
+		}
+	});
+	Ok(quote! {
+		#[automatically_derived]
+		impl #impl_generics ::scf::FromScf<'scf> for #ident #ty_generics #where_clause {
+			fn from_items(
+				it: &mut ::scf::GroupsIter<'scf, '_>,
+			) -> ::core::result::Result<Self, ::scf::DecodeError> {
+				#from_items
+			}
+
+			#from_item
+		}
+	})
+}
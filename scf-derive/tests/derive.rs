@@ -0,0 +1,152 @@
+use scf::{decode, DecodeErrorKind, FromScf, LineCol};
+
+#[derive(FromScf, Debug, PartialEq)]
+struct Device(u16);
+
+#[derive(FromScf, Debug, PartialEq)]
+enum Bus {
+	#[scf(rename = "pci")]
+	Pci {
+		vendor: u16,
+		device: Device,
+	},
+	#[scf(rename = "usb")]
+	Usb(u8, u8),
+	#[scf(rename = "isa")]
+	Isa,
+	Virtual(Option<u32>),
+}
+
+#[derive(FromScf, Debug, PartialEq, Default)]
+struct Common<'a> {
+	#[scf(default)]
+	priority: u8,
+	comment: Option<&'a str>,
+}
+
+#[derive(FromScf, Debug, PartialEq)]
+struct Driver<'a> {
+	bus: Bus,
+	#[scf(rename = "driver-path")]
+	path: &'a str,
+	#[scf(flatten)]
+	common: Common<'a>,
+}
+
+#[derive(FromScf, Debug, PartialEq)]
+struct Point<T>(T, T);
+
+#[derive(FromScf, Debug, PartialEq)]
+struct Outer<'a> {
+	driver: Driver<'a>,
+}
+
+#[test]
+fn structs() {
+	let d: Driver = decode(
+		br#"
+(bus pci (vendor 0x1af4) (device 0x1000))
+(unknown field)
+(driver-path "drivers/pci/virtio/net")
+(priority 3)
+"#,
+	)
+	.unwrap();
+	assert_eq!(
+		d,
+		Driver {
+			bus: Bus::Pci {
+				vendor: 0x1af4,
+				device: Device(0x1000)
+			},
+			path: "drivers/pci/virtio/net",
+			common: Common {
+				priority: 3,
+				comment: None
+			},
+		}
+	);
+	let d: Driver = decode(br#"(comment "x") (driver-path a) (bus (usb 1 2))"#).unwrap();
+	assert_eq!(d.bus, Bus::Usb(1, 2));
+	assert_eq!(
+		d.common,
+		Common {
+			priority: 0,
+			comment: Some("x")
+		}
+	);
+	let p: Point<i8> = decode(b"-1 2").unwrap();
+	assert_eq!(p, Point(-1, 2));
+}
+
+#[test]
+fn nested_flatten() {
+	let o: Outer = decode(b"(driver (priority 2) (bus isa) (driver-path a))").unwrap();
+	assert_eq!(o.driver.bus, Bus::Isa);
+	assert_eq!(o.driver.path, "a");
+	assert_eq!(
+		o.driver.common,
+		Common {
+			priority: 2,
+			comment: None
+		}
+	);
+	let p: Point<Driver> =
+		decode(b"((bus isa) (driver-path a)) ((comment x) (bus isa) (driver-path b))").unwrap();
+	assert_eq!(p.0.common, Common::default());
+	assert_eq!(p.1.common.comment, Some("x"));
+
+	let e = decode::<Outer>(b"(driver (bus isa) (driver-path a) (priority x))").unwrap_err();
+	assert_eq!(e.to_string(), "1:45: expected a u8 in field `priority`");
+}
+
+#[test]
+fn enums() {
+	assert_eq!(decode::<Bus>(b"isa"), Ok(Bus::Isa));
+	assert_eq!(decode::<Bus>(b"(isa)"), Ok(Bus::Isa));
+	assert_eq!(decode::<Bus>(b"Virtual"), Ok(Bus::Virtual(None)));
+	assert_eq!(decode::<Bus>(b"Virtual 5"), Ok(Bus::Virtual(Some(5))));
+	assert_eq!(
+		decode::<Point<Bus>>(b"isa (usb 3 4)"),
+		Ok(Point(Bus::Isa, Bus::Usb(3, 4)))
+	);
+}
+
+#[test]
+fn errors() {
+	let e = decode::<Driver>(b"(bus isa)\n(driver-path (a))").unwrap_err();
+	assert_eq!(e.kind, DecodeErrorKind::Expected("a string"));
+	assert_eq!(e.field, Some("driver-path"));
+	assert_eq!(
+		e.line_col,
+		Some(LineCol {
+			line: 2,
+			column: 14
+		})
+	);
+	assert_eq!(
+		e.to_string(),
+		"2:14: expected a string in field `driver-path`"
+	);
+
+	let e = decode::<Driver>(b"(bus isa)").unwrap_err();
+	assert_eq!(e.to_string(), "1:1: missing field `driver-path`");
+
+	let e = decode::<Driver>(b"(bus\n  (pci (vendor 1) (device x)))").unwrap_err();
+	assert_eq!(e.to_string(), "2:27: expected a u16 in field `device`");
+
+	let e = decode::<Driver>(b"(bus scsi) (driver-path a)").unwrap_err();
+	assert_eq!(e.to_string(), "1:6: unknown variant in field `bus`");
+
+	let e = decode::<Driver>(b"(bus isa) (driver-path a) (priority 1 2)").unwrap_err();
+	assert_eq!(e.to_string(), "1:39: unexpected item in field `priority`");
+
+	let e = decode::<Driver>(b"(bus isa) (bus isa)").unwrap_err();
+	assert_eq!(e.to_string(), "1:12: duplicate field `bus`");
+
+	let e = decode::<Driver>(b"(bus isa) (driver-path a").unwrap_err();
+	assert_eq!(
+		e.kind,
+		DecodeErrorKind::Syntax(scf::ErrorKind::UnclosedGroup)
+	);
+}
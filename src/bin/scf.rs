@@ -0,0 +1,324 @@
+//! Check, format and query S documents.
+
+use scf::{
+	json::{self, GroupStyle, JsonOptions, Repeated},
+	Document, FormatOptions, Value,
+};
+use std::{
+	env, fs,
+	io::{self, Read, Write},
+	process::ExitCode,
+};
+
+const USAGE: &str = "usage: scf check [--permissive] [FILE]...
+       scf fmt [--check] [--permissive] [FILE]...
+       scf get PATH [FILE]
+       scf json [--array] [--repeated=MODE] [--strings] [--pretty] [FILE]
+       scf from-json [--strings] [FILE]
+
+FILE is read from standard input if it is omitted or `-`.
+
+check  Print the position of the first error in each file.
+fmt    Format each file in place, or write to standard output if it is read
+       from standard input. With --check, print the files that would change.
+get    Print the items after the head of the first group at PATH, one per line.
+       PATH is a list of heads separated by `/`, such as `pci-drivers/1af4`.
+json   Convert to JSON. Groups with a head become keys in objects where possible,
+       or with --array, every group becomes an array. MODE decides what happens
+       to heads that occur more than once in a group that would become an
+       object: `array` converts the group to an array instead, `collect` puts
+       their values in an array, `last` keeps the last one and `error` fails.
+       Symbols that look like numbers, booleans or null become those values,
+       unless --strings is given.
+from-json
+       Convert JSON to S. Objects become groups headed by their keys.
+       With --strings, strings that look like numbers, booleans or null
+       aren't quoted.
+
+See the documentation of the `scf::json` module for details.";
+
+/// An invalid command line.
+struct Usage;
+
+fn main() -> ExitCode {
+	let args = env::args().skip(1).collect::<Vec<_>>();
+	match run(&args) {
+		Ok(true) => ExitCode::SUCCESS,
+		Ok(false) => ExitCode::FAILURE,
+		Err(Usage) => {
+			eprintln!("{}", USAGE);
+			ExitCode::from(2)
+		}
+	}
+}
+
+/// Run the command, returning whether it succeeded.
+fn run(args: &[String]) -> Result<bool, Usage> {
+	let (cmd, args) = args.split_first().ok_or(Usage)?;
+	let mut check = false;
+	let mut options = FormatOptions::default();
+	let mut json = JsonOptions::default();
+	let mut operands = Vec::new();
+	for (i, a) in args.iter().enumerate() {
+		match &**a {
+			"--" => {
+				operands.extend(&args[i + 1..]);
+				break;
+			}
+			"--check" if cmd == "fmt" => check = true,
+			"--permissive" if cmd == "check" || cmd == "fmt" => options.permissive = true,
+			"--array" if cmd == "json" => json.groups = GroupStyle::Array,
+			"--pretty" if cmd == "json" => json.pretty = true,
+			"--strings" if cmd == "json" || cmd == "from-json" => json.infer = false,
+			a if cmd == "json" && a.starts_with("--repeated=") => {
+				json.repeated = match &a["--repeated=".len()..] {
+					"array" => Repeated::Array,
+					"collect" => Repeated::Collect,
+					"last" => Repeated::Last,
+					"error" => Repeated::Error,
+					_ => return Err(Usage),
+				}
+			}
+			"-h" | "--help" => return Err(Usage),
+			a if a.starts_with("--") => return Err(Usage),
+			_ => operands.push(a),
+		}
+	}
+	let stdin = ["-".to_string()];
+	let mut ok = true;
+	match &**cmd {
+		"check" | "fmt" => {
+			let files = if operands.is_empty() {
+				stdin.iter().collect()
+			} else {
+				operands
+			};
+			for file in files {
+				let Some(data) = read(file) else {
+					ok = false;
+					continue;
+				};
+				if cmd == "check" {
+					ok &= validate(file, &data, options.permissive);
+				} else {
+					ok &= format(file, &data, &options, check);
+				}
+			}
+		}
+		"get" => {
+			let (path, file) = match &*operands {
+				[path] => (path, &stdin[0]),
+				[path, file] => (path, *file),
+				_ => return Err(Usage),
+			};
+			let Some(data) = read(file) else {
+				return Ok(false);
+			};
+			match get(&data, path) {
+				Ok(Some(out)) => print!("{}", out),
+				Ok(None) => {
+					eprintln!("scf: {}: `{}` not found", name(file), path);
+					ok = false;
+				}
+				Err(e) => {
+					eprintln!("{}:{}", name(file), e);
+					ok = false;
+				}
+			}
+		}
+		"json" | "from-json" => {
+			let file = match &*operands {
+				[] => &stdin[0],
+				[file] => *file,
+				_ => return Err(Usage),
+			};
+			let Some(data) = read(file) else {
+				return Ok(false);
+			};
+			let res = if cmd == "json" {
+				to_json(&data, &json)
+			} else {
+				from_json(&data, &json)
+			};
+			match res {
+				Ok(out) => print!("{}", out),
+				Err(e) => {
+					eprintln!("{}:{}", name(file), e);
+					ok = false;
+				}
+			}
+		}
+		"-h" | "--help" | "help" => {
+			println!("{}", USAGE);
+		}
+		_ => return Err(Usage),
+	}
+	Ok(ok)
+}
+
+/// The name of `file` in messages.
+fn name(file: &str) -> &str {
+	if file == "-" {
+		"<stdin>"
+	} else {
+		file
+	}
+}
+
+/// Read `file`, or standard input if it is `-`, printing an error if that fails.
+fn read(file: &str) -> Option<Vec<u8>> {
+	let data = if file == "-" {
+		let mut data = Vec::new();
+		io::stdin().read_to_end(&mut data).map(|_| data)
+	} else {
+		fs::read(file)
+	};
+	data.map_err(|e| eprintln!("scf: {}: {}", name(file), e))
+		.ok()
+}
+
+/// Print the first error in `data`, if any.
+fn validate(file: &str, data: &[u8], permissive: bool) -> bool {
+	let mut groups = scf::parse2(data);
+	if permissive {
+		groups = groups.permissive();
+	}
+	match Document::from_groups(groups) {
+		Ok(_) => true,
+		Err(e) => {
+			eprintln!("{}:{}", name(file), e);
+			false
+		}
+	}
+}
+
+/// Format `data`, then write it back to `file` or report whether it changed.
+fn format(file: &str, data: &[u8], options: &FormatOptions, check: bool) -> bool {
+	let mut out = String::new();
+	if let Err(e) = scf::format_with(data, &mut out, options) {
+		eprintln!("{}:{}", name(file), e);
+		return false;
+	}
+	let changed = out.as_bytes() != data;
+	let res = match (check, file) {
+		(true, _) => {
+			if changed {
+				println!("{}", name(file));
+			}
+			return !changed;
+		}
+		(false, "-") => io::stdout().write_all(out.as_bytes()),
+		(false, _) if changed => fs::write(file, out),
+		(false, _) => Ok(()),
+	};
+	res.map_err(|e| eprintln!("scf: {}: {}", name(file), e))
+		.is_ok()
+}
+
+/// The items after the head of the first group at `path` in `data`, one per line.
+///
+/// Symbols and strings are printed without quotes or escape sequences.
+fn get(data: &[u8], path: &str) -> Result<Option<String>, scf::Error> {
+	let doc = Document::parse(data)?;
+	Ok(doc.lookup(path).map(|l| {
+		let mut out = String::new();
+		for v in l.tail() {
+			match v {
+				Value::Atom(t) => out += t.as_str(),
+				Value::List(l) => out += &l.to_string(),
+			}
+			out.push('\n');
+		}
+		out
+	}))
+}
+
+/// Convert `data` to JSON, followed by a newline.
+fn to_json(data: &[u8], options: &JsonOptions) -> Result<String, String> {
+	let doc = Document::parse(data).map_err(|e| e.to_string())?;
+	let mut out = json::to_json_string(&doc, options).map_err(|e| match e {
+		json::ToJsonError::Repeated { span, .. } => {
+			format!("{}: {}", span.line_col(data), e)
+		}
+		e => format!(" {}", e),
+	})?;
+	if !options.pretty {
+		out.push('\n');
+	}
+	Ok(out)
+}
+
+/// Convert JSON to a formatted document.
+fn from_json(data: &[u8], options: &JsonOptions) -> Result<String, String> {
+	let doc = json::from_json(data, options).map_err(|e| e.to_string())?;
+	let mut out = String::new();
+	scf::format(doc.to_string().as_bytes(), &mut out).map_err(|e| e.to_string())?;
+	Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn get() {
+		let data = br#"(pci-drivers
+	(1af4 ; Red Hat
+		(1000 "drivers/pci/virtio/net")
+		(1001 "drivers/pci/virtio/blk" (msi "a\tb"))))"#;
+		let get = |path| super::get(data, path).unwrap();
+		assert_eq!(
+			get("pci-drivers/1af4/1000").as_deref(),
+			Some("drivers/pci/virtio/net\n")
+		);
+		assert_eq!(
+			get("pci-drivers/1af4/1001").as_deref(),
+			Some("drivers/pci/virtio/blk\n(msi \"a\\tb\")\n")
+		);
+		assert_eq!(get("pci-drivers/1af4/1001/msi").as_deref(), Some("a\tb\n"));
+		assert_eq!(get("pci-drivers/8086"), None);
+
+		let e = super::get(b"(a (b)", "a").unwrap_err();
+		assert_eq!(e.line_col.to_string(), "1:1");
+	}
+
+	#[test]
+	fn json() {
+		let options = JsonOptions::default();
+		let data = b"(a 1) (b (c x) (c \"y z\"))";
+		assert_eq!(
+			to_json(data, &options).unwrap(),
+			"{\"a\":1,\"b\":[[\"c\",\"x\"],[\"c\",\"y z\"]]}\n"
+		);
+		let error = JsonOptions {
+			repeated: Repeated::Error,
+			..options
+		};
+		assert_eq!(
+			to_json(data, &error).unwrap_err(),
+			"1:16: `b/c` occurs more than once"
+		);
+		assert_eq!(
+			from_json(br#"{"a": 1, "b": [["c", "x"], ["c", "y z"]]}"#, &options).unwrap(),
+			"(a 1)\n(b (c x) (c \"y z\"))\n"
+		);
+		assert_eq!(
+			from_json(b"[1,", &options).unwrap_err(),
+			"1:4: invalid JSON, expected a value"
+		);
+	}
+
+	#[test]
+	fn usage() {
+		let args = |a: &[&str]| run(&a.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+		assert!(args(&[]).is_err());
+		assert!(args(&["lint"]).is_err());
+		assert!(args(&["check", "--check"]).is_err());
+		assert!(args(&["get"]).is_err());
+		assert!(args(&["get", "a", "b", "c"]).is_err());
+		assert!(args(&["get", "--strings", "a"]).is_err());
+		assert!(args(&["json", "--repeated=first"]).is_err());
+		assert!(args(&["from-json", "--array"]).is_err());
+		assert!(args(&["fmt", "--check", "--", "--nonexistent"]).is_ok_and(|ok| !ok));
+	}
+}
@@ -0,0 +1,882 @@
+//! A lossless syntax tree that keeps comments and whitespace.
+//!
+//! Every byte of the input belongs to exactly one node or to the trivia at the end of a group,
+//! so printing a [`Tree`] with [`Display`](fmt::Display) gives back the input exactly.
+//!
+//! Comments belong to the node after them, unless they are separated from it by a blank line.
+//! A comment on the same line after a node belongs to that node.
+//! Moving or removing a node takes its comments with it.
+//! When printing, a newline is added after a trailing comment if something follows on its line.
+//!
+//! Nodes can be edited by [`KeyPath`] or by [`Cursor`] with the methods of [`Group`].
+//! Everything that isn't edited stays byte-identical.
+//!
+//! ```
+//! use scf::cst::{Node, Tree};
+//!
+//! let mut tree = Tree::parse(b"(pci-drivers\n\t(1af4 ; Red Hat\n\t\t(1000 net)))\n").unwrap();
+//! let vendor = tree.lookup_mut("pci-drivers/1af4").unwrap();
+//! vendor.insert(2, Node::group([Node::atom("1001"), Node::atom("blk")]));
+//! assert_eq!(
+//!     tree.to_string(),
+//!     "(pci-drivers\n\t(1af4 ; Red Hat\n\t\t(1000 net)\n\t\t(1001 blk)))\n"
+//! );
+//! ```
+
+use crate::{Error, ErrorKind, Iter, KeyPath, Span, Token};
+use alloc::{borrow::Cow, string::String, vec::Vec};
+use core::{fmt, ops, str};
+
+/// A document.
+///
+/// This dereferences to a [`Group`] without parentheses.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Tree<'a> {
+	pub root: Group<'a>,
+}
+
+impl<'a> Tree<'a> {
+	/// Parse a document. The whole document must be valid UTF-8, including comments.
+	pub fn parse(data: &'a [u8]) -> Result<Self, Error> {
+		let mut cf = crate::parse2(data);
+		for _ in cf.iter() {}
+		if let Some(e) = cf.into_error() {
+			return Err(e);
+		}
+		let data = str::from_utf8(data)
+			.map_err(|e| Error::new(data, ErrorKind::InvalidUtf8, e.valid_up_to()))?;
+		let mut b = Builder {
+			data,
+			it: Iter::new(data.as_bytes()),
+			prev: 0,
+		};
+		Ok(Self { root: b.group() })
+	}
+
+	pub fn into_owned(self) -> Tree<'static> {
+		Tree {
+			root: self.root.into_owned(),
+		}
+	}
+}
+
+impl<'a> ops::Deref for Tree<'a> {
+	type Target = Group<'a>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.root
+	}
+}
+
+impl ops::DerefMut for Tree<'_> {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.root
+	}
+}
+
+impl fmt::Display for Tree<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.root.fmt_inner(f, None)
+	}
+}
+
+/// An atom or group together with the trivia around it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Node<'a> {
+	/// Whitespace and comments between the previous node and this one.
+	pub leading: Cow<'a, str>,
+	pub kind: NodeKind<'a>,
+	/// A comment on the same line after this node, including the whitespace before it.
+	/// Empty if there is no such comment.
+	pub trailing: Cow<'a, str>,
+	/// The location in the parsed data, or an empty span for nodes that were created or edited.
+	pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NodeKind<'a> {
+	/// The source text of a symbol or string, including quotes.
+	Atom(Cow<'a, str>),
+	Group(Group<'a>),
+}
+
+impl<'a> Node<'a> {
+	fn new(kind: NodeKind<'a>) -> Self {
+		Self {
+			leading: "".into(),
+			kind,
+			trailing: "".into(),
+			span: Span::default(),
+		}
+	}
+
+	/// An atom, quoted only if necessary.
+	pub fn atom(s: &str) -> Self {
+		let mut text = String::new();
+		let _ = crate::write_atom(&mut text, s);
+		Self::new(NodeKind::Atom(text.into()))
+	}
+
+	/// An atom that is always quoted.
+	pub fn quoted(s: &str) -> Self {
+		let mut text = String::new();
+		let _ = crate::write_quoted(&mut text, s);
+		Self::new(NodeKind::Atom(text.into()))
+	}
+
+	/// A group with the items separated by a space.
+	pub fn group(items: impl IntoIterator<Item = Node<'a>>) -> Self {
+		let mut g = Group::default();
+		for n in items {
+			g.insert(g.items.len(), n);
+		}
+		Self::new(NodeKind::Group(g))
+	}
+
+	/// The text of an atom, without quotes. Escape sequences are not decoded.
+	pub fn as_str(&self) -> Option<&str> {
+		match &self.kind {
+			NodeKind::Atom(s) => Some(unquote(s)),
+			NodeKind::Group(_) => None,
+		}
+	}
+
+	pub fn as_group(&self) -> Option<&Group<'a>> {
+		match &self.kind {
+			NodeKind::Group(g) => Some(g),
+			NodeKind::Atom(_) => None,
+		}
+	}
+
+	pub fn as_group_mut(&mut self) -> Option<&mut Group<'a>> {
+		match &mut self.kind {
+			NodeKind::Group(g) => Some(g),
+			NodeKind::Atom(_) => None,
+		}
+	}
+
+	/// All comments that belong to this node, including the leading `;`.
+	pub fn comments(&self) -> impl Iterator<Item = &str> {
+		let (_, attached) = split_leading(&self.leading);
+		comments(attached).chain(comments(&self.trailing))
+	}
+
+	/// The comment on the same line after this node, if any.
+	pub fn trailing_comment(&self) -> Option<&str> {
+		comments(&self.trailing).next()
+	}
+
+	/// Replace the comment on the same line after this node.
+	///
+	/// # Panics
+	///
+	/// If `comment` contains a newline.
+	pub fn set_trailing_comment(&mut self, comment: Option<&str>) {
+		self.trailing = match comment {
+			Some(c) => {
+				assert!(!c.contains('\n'), "comment contains a newline");
+				alloc::format!(" ; {}", c).into()
+			}
+			None => "".into(),
+		};
+	}
+
+	/// Add a comment on its own line above this node, at the same indentation.
+	///
+	/// # Panics
+	///
+	/// If `comment` contains a newline.
+	pub fn add_comment(&mut self, comment: &str) {
+		assert!(!comment.contains('\n'), "comment contains a newline");
+		let indent_start = self.leading.rfind('\n').map_or(0, |i| i + 1);
+		let (before, indent) = self.leading.split_at(indent_start);
+		let indent = if indent.trim().is_empty() { indent } else { "" };
+		self.leading = alloc::format!("{}{}; {}\n{}", before, indent, comment, indent).into();
+	}
+
+	/// Remove all comments that belong to this node.
+	///
+	/// Lines that only held a comment are removed as well.
+	pub fn remove_comments(&mut self) {
+		let (detached, attached) = split_leading(&self.leading);
+		if comments(attached).next().is_some() {
+			let mut s = String::from(detached);
+			for (i, line) in attached.split('\n').enumerate() {
+				if line.trim_start().starts_with(';') {
+					continue;
+				}
+				if i > 0 {
+					s.push('\n');
+				}
+				s.push_str(line);
+			}
+			self.leading = s.into();
+		}
+		self.trailing = "".into();
+	}
+
+	pub fn into_owned(self) -> Node<'static> {
+		Node {
+			leading: self.leading.into_owned().into(),
+			kind: match self.kind {
+				NodeKind::Atom(s) => NodeKind::Atom(s.into_owned().into()),
+				NodeKind::Group(g) => NodeKind::Group(g.into_owned()),
+			},
+			trailing: self.trailing.into_owned().into(),
+			span: self.span,
+		}
+	}
+}
+
+impl<'a> Node<'a> {
+	/// Write the node, where `indent` is the indentation of the line it starts on.
+	fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, indent: &str) -> fmt::Result {
+		f.write_str(&self.leading)?;
+		match &self.kind {
+			NodeKind::Atom(s) => f.write_str(s)?,
+			NodeKind::Group(g) => {
+				f.write_str("(")?;
+				g.fmt_inner(f, Some(indent))?;
+				f.write_str(")")?;
+			}
+		}
+		f.write_str(&self.trailing)
+	}
+}
+
+impl fmt::Display for Node<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.fmt_indented(f, line_indent(&self.leading).unwrap_or(""))
+	}
+}
+
+/// The contents of a group or document.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Group<'a> {
+	pub items: Vec<Node<'a>>,
+	/// Whitespace and comments after the last item.
+	pub end: Cow<'a, str>,
+}
+
+impl<'a> Group<'a> {
+	/// The text of the first item if it is an atom.
+	pub fn head(&self) -> Option<&str> {
+		self.items.first().and_then(Node::as_str)
+	}
+
+	/// The first group in this group with the given head.
+	pub fn get(&self, head: &str) -> Option<&Group<'a>> {
+		self.items
+			.iter()
+			.filter_map(Node::as_group)
+			.find(|g| g.head() == Some(head))
+	}
+
+	pub fn get_mut(&mut self, head: &str) -> Option<&mut Group<'a>> {
+		self.items
+			.iter_mut()
+			.filter_map(Node::as_group_mut)
+			.find(|g| g.head() == Some(head))
+	}
+
+	/// The first group at `path`. See [`KeyPath`].
+	pub fn lookup<P: KeyPath>(&self, path: P) -> Option<&Group<'a>> {
+		let mut g = self;
+		for i in 0.. {
+			let Some(head) = path.segment(i) else {
+				return (i > 0).then_some(g);
+			};
+			g = g.get(head)?;
+		}
+		None
+	}
+
+	pub fn lookup_mut<P: KeyPath>(&mut self, path: P) -> Option<&mut Group<'a>> {
+		let mut g = self;
+		for i in 0.. {
+			let Some(head) = path.segment(i) else {
+				return (i > 0).then_some(g);
+			};
+			g = g.get_mut(head)?;
+		}
+		None
+	}
+
+	/// Insert a node at `index`.
+	///
+	/// If the node has no leading trivia it is laid out like its siblings:
+	/// on its own line with the same indentation if they are, separated by a space otherwise.
+	///
+	/// # Panics
+	///
+	/// If `index` is out of bounds.
+	pub fn insert(&mut self, index: usize, mut node: Node<'a>) {
+		assert!(index <= self.items.len(), "index out of bounds");
+		if node.leading.is_empty() && !self.items.is_empty() {
+			// Prefer a sibling that isn't the head, which usually shares a line with `(`.
+			let sibling = match index {
+				0 | 1 => self.items.get(1),
+				i => self.items.get(i - 1),
+			};
+			node.leading = String::from(sibling.map_or(" ", |s| layout(&s.leading))).into();
+			if index == 0 {
+				// The node becomes the head.
+				node.leading = "".into();
+			}
+		}
+		self.items.insert(index, node);
+		self.fix(index);
+		self.fix(index + 1);
+	}
+
+	/// Remove the node at `index` together with its comments.
+	///
+	/// Comments above the node that are separated from it by a blank line stay.
+	///
+	/// # Panics
+	///
+	/// If `index` is out of bounds.
+	pub fn remove(&mut self, index: usize) -> Node<'a> {
+		let mut node = self.items.remove(index);
+		let (detached, attached) = split_leading(&node.leading);
+		if let (0, Some(next)) = (index, self.items.first_mut()) {
+			// The next node takes the place of the removed one, e.g. as the head.
+			let space = &attached[..attached.len() - attached.trim_start().len()];
+			let comments = next.leading.trim_start();
+			next.leading = alloc::format!("{}{}{}", detached, space, comments).into();
+			node.leading = String::from(&attached[space.len()..]).into();
+		} else if !detached.is_empty() {
+			let next = match self.items.get_mut(index) {
+				Some(n) => &mut n.leading,
+				None => &mut self.end,
+			};
+			let rest = next.trim_start_matches([' ', '\t']);
+			let rest = rest.strip_prefix('\n').unwrap_or(next);
+			*next = alloc::format!("{}{}", detached, rest).into();
+			node.leading = String::from(attached).into();
+		}
+		self.fix(index);
+		node
+	}
+
+	/// Move the node at `from` to `to`, together with its comments.
+	///
+	/// A node without comments above it is laid out like its new siblings,
+	/// as with [`Group::insert`].
+	///
+	/// # Panics
+	///
+	/// If `from` or `to` is out of bounds.
+	pub fn move_item(&mut self, from: usize, to: usize) {
+		let mut node = self.remove(from);
+		assert!(to <= self.items.len(), "index out of bounds");
+		if node.leading.trim().is_empty() {
+			node.leading = "".into();
+		}
+		self.insert(to, node);
+	}
+
+	/// Make sure the node at `index` is separated from the one before it.
+	fn fix(&mut self, index: usize) {
+		if let Some(n) = self.items.get_mut(index) {
+			if index > 0 && n.leading.is_empty() {
+				n.leading = " ".into();
+			}
+		}
+	}
+
+	pub fn into_owned(self) -> Group<'static> {
+		Group {
+			items: self.items.into_iter().map(Node::into_owned).collect(),
+			end: self.end.into_owned().into(),
+		}
+	}
+
+	/// Write the items and trailing trivia.
+	///
+	/// `close` is the indentation of the line with the opening parenthesis,
+	/// or `None` for a document.
+	///
+	/// A newline is added after a trailing comment if needed so it doesn't hide what follows.
+	/// A closing parenthesis after it is indented like the opening parenthesis.
+	fn fmt_inner(&self, f: &mut fmt::Formatter<'_>, close: Option<&str>) -> fmt::Result {
+		let mut comment = false;
+		let mut indent = close.unwrap_or("");
+		for n in &self.items {
+			if comment && !n.leading.starts_with('\n') {
+				f.write_str("\n")?;
+			}
+			indent = line_indent(&n.leading).unwrap_or(indent);
+			n.fmt_indented(f, indent)?;
+			comment = !n.trailing.is_empty();
+		}
+		match close {
+			Some(indent) if comment && !self.end.starts_with('\n') => {
+				write!(f, "\n{}", indent)?;
+			}
+			_ => {}
+		}
+		f.write_str(&self.end)
+	}
+}
+
+impl<'a> ops::Index<usize> for Group<'a> {
+	type Output = Node<'a>;
+
+	fn index(&self, index: usize) -> &Self::Output {
+		&self.items[index]
+	}
+}
+
+impl ops::IndexMut<usize> for Group<'_> {
+	fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+		&mut self.items[index]
+	}
+}
+
+/// The location of a node as the index of the node and of every group around it.
+///
+/// An empty cursor refers to the document itself, which has no node.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cursor {
+	pub indices: Vec<usize>,
+}
+
+impl Cursor {
+	pub fn new(indices: impl Into<Vec<usize>>) -> Self {
+		Self {
+			indices: indices.into(),
+		}
+	}
+
+	/// The cursor of the item at `index` in the group at this cursor.
+	pub fn child(&self, index: usize) -> Self {
+		let mut c = self.clone();
+		c.indices.push(index);
+		c
+	}
+
+	/// The cursor of the group around this node, if any.
+	pub fn parent(&self) -> Option<Self> {
+		let (_, parent) = self.indices.split_last()?;
+		Some(Self::new(parent))
+	}
+
+	/// The index of this node in its group.
+	pub fn index(&self) -> Option<usize> {
+		self.indices.last().copied()
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditError {
+	/// There is no node at the given path or cursor.
+	NotFound,
+	/// The node is an atom but a group is required.
+	NotAGroup,
+}
+
+impl fmt::Display for EditError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::NotFound => "node not found",
+			Self::NotAGroup => "node is not a group",
+		})
+	}
+}
+
+impl core::error::Error for EditError {}
+
+/// Editing.
+///
+/// Only the nodes that are edited change. The trivia of all other nodes,
+/// including comments and indentation, is left as is.
+impl<'a> Group<'a> {
+	/// The cursor of the first group at `path`. See [`KeyPath`].
+	pub fn cursor<P: KeyPath>(&self, path: P) -> Option<Cursor> {
+		let (mut g, mut c) = (self, Cursor::default());
+		for i in 0.. {
+			let Some(head) = path.segment(i) else {
+				return (i > 0).then_some(c);
+			};
+			let j = position(g, head)?;
+			c.indices.push(j);
+			g = g.items[j].as_group()?;
+		}
+		None
+	}
+
+	fn group_at(&self, indices: &[usize]) -> Result<&Group<'a>, EditError> {
+		indices.iter().try_fold(self, |g, &i| {
+			let n = g.items.get(i).ok_or(EditError::NotFound)?;
+			n.as_group().ok_or(EditError::NotAGroup)
+		})
+	}
+
+	fn group_at_mut(&mut self, indices: &[usize]) -> Result<&mut Group<'a>, EditError> {
+		indices.iter().try_fold(self, |g, &i| {
+			let n = g.items.get_mut(i).ok_or(EditError::NotFound)?;
+			n.as_group_mut().ok_or(EditError::NotAGroup)
+		})
+	}
+
+	/// The group containing the node at `at` and the index of the node in it.
+	fn parent_mut(&mut self, at: &Cursor) -> Result<(&mut Group<'a>, usize), EditError> {
+		let (&i, parent) = at.indices.split_last().ok_or(EditError::NotFound)?;
+		Ok((self.group_at_mut(parent)?, i))
+	}
+
+	pub fn node(&self, at: &Cursor) -> Option<&Node<'a>> {
+		let (&i, parent) = at.indices.split_last()?;
+		self.group_at(parent).ok()?.items.get(i)
+	}
+
+	pub fn node_mut(&mut self, at: &Cursor) -> Option<&mut Node<'a>> {
+		let (g, i) = self.parent_mut(at).ok()?;
+		g.items.get_mut(i)
+	}
+
+	/// Replace the node at `at` and return the old node.
+	///
+	/// If the new node has no leading or trailing trivia it takes that of the old node,
+	/// so the layout and comments around it are kept.
+	pub fn replace(&mut self, at: &Cursor, mut node: Node<'a>) -> Result<Node<'a>, EditError> {
+		let old = self.node_mut(at).ok_or(EditError::NotFound)?;
+		if node.leading.is_empty() {
+			node.leading = core::mem::take(&mut old.leading);
+		}
+		if node.trailing.is_empty() {
+			node.trailing = core::mem::take(&mut old.trailing);
+		}
+		Ok(core::mem::replace(old, node))
+	}
+
+	/// Insert a node so it ends up at `at`. See [`Group::insert`].
+	pub fn insert_at(&mut self, at: &Cursor, node: Node<'a>) -> Result<(), EditError> {
+		let (g, i) = self.parent_mut(at)?;
+		if i > g.items.len() {
+			return Err(EditError::NotFound);
+		}
+		g.insert(i, node);
+		Ok(())
+	}
+
+	/// Remove the node at `at` with everything in it. See [`Group::remove`].
+	pub fn remove_at(&mut self, at: &Cursor) -> Result<Node<'a>, EditError> {
+		let (g, i) = self.parent_mut(at)?;
+		if i >= g.items.len() {
+			return Err(EditError::NotFound);
+		}
+		Ok(g.remove(i))
+	}
+
+	/// Set the value of the group at `path`, i.e. replace everything after its head with `value`.
+	///
+	/// The new value takes the place of the old value if that was an atom,
+	/// and is written as a double-quoted string if the old value was quoted.
+	pub fn set<P: KeyPath>(&mut self, path: P, value: &str) -> Result<(), EditError> {
+		let at = self.cursor(path).ok_or(EditError::NotFound)?;
+		let g = self.group_at_mut(&at.indices)?;
+		while g.items.len() > 2 {
+			g.remove(g.items.len() - 1);
+		}
+		match g.items.get(1).map(|n| &n.kind) {
+			Some(NodeKind::Atom(s)) => {
+				let quoted = s.starts_with(['"', '\'']);
+				let node = if quoted {
+					Node::quoted(value)
+				} else {
+					Node::atom(value)
+				};
+				g.replace(&Cursor::new([1]), node)?;
+			}
+			Some(NodeKind::Group(_)) => {
+				g.replace(&Cursor::new([1]), Node::atom(value))?;
+			}
+			None => g.insert(1, Node::atom(value)),
+		}
+		Ok(())
+	}
+
+	/// Add a node to the end of the group at `path`.
+	pub fn append<P: KeyPath>(&mut self, path: P, node: Node<'a>) -> Result<(), EditError> {
+		let g = self.lookup_mut(path).ok_or(EditError::NotFound)?;
+		g.insert(g.items.len(), node);
+		Ok(())
+	}
+
+	/// Remove the first group at `path` with everything in it.
+	pub fn delete<P: KeyPath>(&mut self, path: P) -> Result<Node<'a>, EditError> {
+		let at = self.cursor(path).ok_or(EditError::NotFound)?;
+		self.remove_at(&at)
+	}
+}
+
+/// The index of the first group in `g` with the given head.
+fn position(g: &Group<'_>, head: &str) -> Option<usize> {
+	g.items
+		.iter()
+		.position(|n| n.as_group().is_some_and(|g| g.head() == Some(head)))
+}
+
+fn unquote(s: &str) -> &str {
+	match s.as_bytes().first() {
+		Some(b'"' | b'\'') => &s[1..s.len() - 1],
+		_ => s,
+	}
+}
+
+fn comments(trivia: &str) -> impl Iterator<Item = &str> {
+	trivia
+		.split('\n')
+		.map(str::trim)
+		.filter(|l| l.starts_with(';'))
+}
+
+/// The whitespace that puts a node in the same place as one with the given leading trivia.
+fn layout(leading: &str) -> &str {
+	match leading.rfind('\n') {
+		Some(i) if leading[i..].trim().is_empty() => &leading[i..],
+		Some(_) => "\n",
+		None => " ",
+	}
+}
+
+/// The indentation after the last newline in `leading`, if any.
+fn line_indent(leading: &str) -> Option<&str> {
+	leading.rfind('\n').map(|i| &leading[i + 1..])
+}
+
+/// Split leading trivia after the last blank line.
+fn split_leading(leading: &str) -> (&str, &str) {
+	let mut split = 0;
+	let mut line_start = None;
+	for (i, _) in leading.match_indices('\n') {
+		if line_start.is_some_and(|s| leading[s..i].trim().is_empty()) {
+			split = i + 1;
+		}
+		line_start = Some(i + 1);
+	}
+	leading.split_at(split)
+}
+
+struct Builder<'a> {
+	data: &'a str,
+	it: Iter<'a>,
+	/// The end of the last token or trailing comment.
+	prev: usize,
+}
+
+impl<'a> Builder<'a> {
+	fn group(&mut self) -> Group<'a> {
+		let mut items = Vec::new();
+		loop {
+			// The document has already been validated.
+			let Some(Ok((tk, span))) = self.it.next_spanned() else {
+				let end = self.data[self.prev..].into();
+				self.prev = self.data.len();
+				return Group { items, end };
+			};
+			let leading = self.data[self.prev..span.start].into();
+			self.prev = span.end;
+			let (kind, span) = match tk {
+				Token::End => {
+					return Group {
+						items,
+						end: leading,
+					}
+				}
+				Token::Begin => {
+					let g = self.group();
+					let span = Span {
+						start: span.start,
+						end: self.prev,
+					};
+					(NodeKind::Group(g), span)
+				}
+				_ => (NodeKind::Atom(self.data[span.start..span.end].into()), span),
+			};
+			let trailing = self.trailing();
+			items.push(Node {
+				leading,
+				kind,
+				trailing,
+				span,
+			});
+		}
+	}
+
+	fn trailing(&mut self) -> Cow<'a, str> {
+		let rest = &self.data[self.prev..];
+		if !rest.trim_start_matches([' ', '\t']).starts_with(';') {
+			return "".into();
+		}
+		let len = rest.find('\n').unwrap_or(rest.len());
+		self.prev += len;
+		rest[..len].into()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use alloc::string::ToString;
+
+	const PCI: &str = r#"; PCI drivers
+
+(pci-drivers
+	(1af4 ; Red Hat
+		(1000 "drivers/pci/virtio/net")
+		; block devices
+		(1001 "drivers/pci/virtio/blk")
+		(1040 'drivers/pci/virtio/gpu'))
+	(8086   ; Intel
+		(1616 "drivers/pci/intel/hd graphics" )) ; trailing
+  )  ; end
+  ; final
+"#;
+
+	#[test]
+	fn roundtrip() {
+		let tree = Tree::parse(PCI.as_bytes()).unwrap();
+		assert_eq!(tree.to_string(), PCI);
+		for s in [
+			"",
+			" ",
+			"a",
+			"; x",
+			"(a ;b\n)",
+			"(a\r\nb) ; c\r\n",
+			"((()))",
+			"\"\\n\" 'x'",
+		] {
+			assert_eq!(Tree::parse(s.as_bytes()).unwrap().to_string(), s);
+		}
+		assert_eq!(
+			Tree::parse(b"(a").unwrap_err().kind,
+			ErrorKind::UnclosedGroup
+		);
+		assert_eq!(
+			Tree::parse(b"a ; \xff").unwrap_err().kind,
+			ErrorKind::InvalidUtf8
+		);
+	}
+
+	#[test]
+	fn structure() {
+		let tree = Tree::parse(PCI.as_bytes()).unwrap();
+		let drivers = tree.get("pci-drivers").unwrap();
+		let rh = drivers.get("1af4").unwrap();
+		assert_eq!(rh[0].trailing_comment(), Some("; Red Hat"));
+		let blk = &rh[2];
+		assert_eq!(blk.comments().collect::<Vec<_>>(), ["; block devices"]);
+		assert_eq!(
+			blk.as_group().unwrap()[1].as_str(),
+			Some("drivers/pci/virtio/blk")
+		);
+		assert_eq!(
+			rh[3].as_group().unwrap()[1].as_str(),
+			Some("drivers/pci/virtio/gpu")
+		);
+		assert_eq!(tree[0].comments().collect::<Vec<_>>(), ["; end"]);
+		assert_eq!(tree[0].trailing_comment(), Some("; end"));
+		assert_eq!(tree.end, "\n  ; final\n");
+		assert_eq!(drivers[2].trailing_comment(), Some("; trailing"));
+		let intel = tree.lookup("pci-drivers/8086").unwrap();
+		assert_eq!(intel[1].span.len(), 39);
+	}
+
+	#[test]
+	fn comments() {
+		let mut tree = Tree::parse(PCI.as_bytes()).unwrap();
+		let rh = tree.lookup_mut("pci-drivers/1af4").unwrap();
+		rh.move_item(2, 3);
+		rh[3].set_trailing_comment(Some("moved"));
+		rh[1].add_comment("network");
+		rh[0].set_trailing_comment(None);
+		let expected = PCI
+			.replace(
+				"\t(1af4 ; Red Hat\n\t\t(1000",
+				"\t(1af4\n\t\t; network\n\t\t(1000",
+			)
+			.replace(
+				"\t\t; block devices\n\t\t(1001 \"drivers/pci/virtio/blk\")\n\t\t(1040 'drivers/pci/virtio/gpu'))",
+				"\t\t(1040 'drivers/pci/virtio/gpu')\n\t\t; block devices\n\t\t(1001 \"drivers/pci/virtio/blk\") ; moved\n\t)",
+			);
+		assert_eq!(tree.to_string(), expected);
+
+		let mut tree = Tree::parse(PCI.as_bytes()).unwrap();
+		let node = tree.remove(0);
+		assert_eq!(node.comments().count(), 1);
+		assert_eq!(tree.to_string(), "; PCI drivers\n\n  ; final\n");
+
+		let mut tree = Tree::parse(b"(a b ; x\n c)").unwrap();
+		let g = tree[0].as_group_mut().unwrap();
+		g.move_item(1, 2);
+		assert_eq!(tree.to_string(), "(a\n c\n b ; x\n)");
+		let g = tree[0].as_group_mut().unwrap();
+		g[2].remove_comments();
+		g.move_item(0, 2);
+		assert_eq!(tree.to_string(), "(c\n b\n a)");
+
+		let mut tree = Tree::parse(b"(a b) (x\n\t(y 1 ; one\n\t\t2))").unwrap();
+		tree[0].as_group_mut().unwrap().remove(0);
+		let y = tree.lookup_mut("x/y").unwrap();
+		y.move_item(1, 2);
+		assert_eq!(tree.to_string(), "(b) (x\n\t(y\n\t\t2\n\t\t1 ; one\n\t))");
+		let y = tree.lookup_mut("x/y").unwrap();
+		y.remove(0);
+		assert_eq!(tree.to_string(), "(b) (x\n\t(2\n\t\t1 ; one\n\t))");
+	}
+
+	#[test]
+	fn edit() {
+		let mut tree = Tree::parse(PCI.as_bytes()).unwrap();
+		tree.set("pci-drivers/1af4/1001", "drivers/pci/virtio/block")
+			.unwrap();
+		tree.append(
+			"pci-drivers/8086",
+			Node::group([Node::atom("1617"), Node::quoted("i915")]),
+		)
+		.unwrap();
+		tree.delete("pci-drivers/1af4/1040").unwrap();
+		let expected = PCI
+			.replace("virtio/blk\"", "virtio/block\"")
+			.replace("\n\t\t(1040 'drivers/pci/virtio/gpu')", "")
+			.replace("graphics\" ))", "graphics\" )\n\t\t(1617 \"i915\"))");
+		assert_eq!(tree.to_string(), expected);
+		assert_eq!(tree.set("pci-drivers/ffff", "x"), Err(EditError::NotFound));
+
+		let at = tree.cursor("pci-drivers/1af4").unwrap();
+		assert_eq!(at, Cursor::new([0, 1]));
+		let old = tree.replace(&at.child(0), Node::atom("1AF4")).unwrap();
+		assert_eq!(old.as_str(), Some("1af4"));
+		assert_eq!(
+			tree.node(&at.child(0)).unwrap().trailing_comment(),
+			Some("; Red Hat")
+		);
+		let blk = tree.remove_at(&at.child(2)).unwrap();
+		tree.insert_at(&at.child(1), blk).unwrap();
+		assert_eq!(
+			tree.node(&at).unwrap().to_string(),
+			"\n\t(1AF4 ; Red Hat\n\t\t; block devices\n\t\t(1001 \"drivers/pci/virtio/block\")\n\t\t(1000 \"drivers/pci/virtio/net\"))"
+		);
+		assert_eq!(
+			tree.insert_at(&at.child(0).child(0), Node::atom("x")),
+			Err(EditError::NotAGroup)
+		);
+		assert_eq!(
+			tree.remove_at(&at.child(9)).unwrap_err(),
+			EditError::NotFound
+		);
+
+		let mut tree = Tree::parse(b"(a) (b 1 2 3) (c)").unwrap();
+		tree.set("b", "x y").unwrap();
+		tree.set("c", "z").unwrap();
+		assert_eq!(tree.to_string(), "(a) (b \"x y\") (c z)");
+
+		let mut tree = Tree::parse(b"(a 'x')").unwrap();
+		tree.set("a", "y").unwrap();
+		assert_eq!(tree.to_string(), "(a \"y\")");
+	}
+}
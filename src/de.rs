@@ -0,0 +1,683 @@
+//! Deserialize S documents with [`serde`].
+//!
+//! # Conventions
+//!
+//! A value is read either from a single item or from a sequence of items,
+//! depending on where it appears.
+//! The top level of a document and the items after the head of a field
+//! are sequences of items. Everything else is a single item.
+//!
+//! - Booleans, numbers, characters and strings are single symbols or strings.
+//!   Integers may have a `0x`, `0o` or `0b` prefix.
+//! - Structs and maps are a `(field value)` group per field.
+//!   The value of a field is everything after its head, so `(ports 80 443)`
+//!   is a field `ports` with a sequence of two elements.
+//!   Unknown fields are ignored.
+//! - Sequences and tuples are lists: `(80 443)` as a single item
+//!   or `80 443` as a sequence of items.
+//! - Options are `None` if there are no items, i.e. `(field)` or a missing field.
+//!   As a single item `()` is `None`.
+//! - Unit and unit structs are `()` or no items at all.
+//! - Enum variants are the head symbol of a group: `(Rgb 1 2 3)`
+//!   or `(Point (x 1) (y 2))`. Unit variants may be a bare symbol: `Red`.
+//!   In a sequence of items the group may be left out: `(color Rgb 1 2 3)`.
+//! - Newtype structs are transparent.
+//!
+//! ```
+//! #[derive(serde::Deserialize, Debug, PartialEq)]
+//! struct Driver<'a> {
+//!     vendor: u16,
+//!     device: u16,
+//!     path: &'a str,
+//! }
+//!
+//! #[derive(serde::Deserialize, Debug, PartialEq)]
+//! struct Config<'a> {
+//!     #[serde(borrow)]
+//!     drivers: Vec<Driver<'a>>,
+//! }
+//!
+//! let cfg: Config = scf::from_str(r#"
+//! (drivers
+//!     ((vendor 0x1af4) (device 0x1000) (path "drivers/pci/virtio/net")))
+//! "#).unwrap();
+//! assert_eq!(cfg.drivers[0].path, "drivers/pci/virtio/net");
+//! ```
+
+use crate::{Document, LineCol, List, Span, Text, Value};
+use alloc::{
+	borrow::Cow,
+	string::{String, ToString},
+};
+use core::{fmt, str::FromStr};
+use serde::de::{self, DeserializeSeed, Visitor};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+	message: String,
+	offset: Option<usize>,
+	line_col: Option<LineCol>,
+}
+
+impl Error {
+	pub fn message(&self) -> &str {
+		&self.message
+	}
+
+	/// The byte offset of the value that caused the error, if known.
+	pub fn offset(&self) -> Option<usize> {
+		self.offset
+	}
+
+	pub fn line_col(&self) -> Option<LineCol> {
+		self.line_col
+	}
+
+	fn at(mut self, data: &[u8], span: Span) -> Self {
+		if self.offset.is_none() {
+			self.offset = Some(span.start);
+			self.line_col = Some(span.line_col(data));
+		}
+		self
+	}
+}
+
+impl From<crate::Error> for Error {
+	fn from(e: crate::Error) -> Self {
+		Self {
+			message: e.kind.to_string(),
+			offset: Some(e.offset),
+			line_col: Some(e.line_col),
+		}
+	}
+}
+
+impl de::Error for Error {
+	fn custom<T: fmt::Display>(msg: T) -> Self {
+		Self {
+			message: msg.to_string(),
+			offset: None,
+			line_col: None,
+		}
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.line_col {
+			Some(lc) => write!(f, "{}: {}", lc, self.message),
+			None => f.write_str(&self.message),
+		}
+	}
+}
+
+impl core::error::Error for Error {}
+
+/// Deserialize an instance of `T` from a document.
+pub fn from_str<'de, T: de::Deserialize<'de>>(s: &'de str) -> Result<T, Error> {
+	from_slice(s.as_bytes())
+}
+
+/// Deserialize an instance of `T` from a document.
+pub fn from_slice<'de, T: de::Deserialize<'de>>(data: &'de [u8]) -> Result<T, Error> {
+	let doc = Document::parse(data)?;
+	T::deserialize(Deserializer::new(data, &doc))
+}
+
+#[derive(Clone, Copy)]
+enum Slot<'v, 'de> {
+	/// A single item.
+	Item(&'v Value<'de>),
+	/// A sequence of items, with the span of the group they are in.
+	Items(&'v [Value<'de>], Span),
+}
+
+/// A deserializer for a [`Document`] or a value inside of it.
+#[derive(Clone, Copy)]
+pub struct Deserializer<'v, 'de> {
+	data: &'de [u8],
+	slot: Slot<'v, 'de>,
+}
+
+impl<'v, 'de> Deserializer<'v, 'de> {
+	/// `data` is the source of the document and is used for error positions.
+	pub fn new(data: &'de [u8], doc: &'v Document<'de>) -> Self {
+		Self::items(data, &doc.root)
+	}
+
+	/// Deserialize from all items of `list`.
+	pub fn items(data: &'de [u8], list: &'v List<'de>) -> Self {
+		Self {
+			data,
+			slot: Slot::Items(&list.items, list.span),
+		}
+	}
+
+	/// Deserialize from a single value.
+	pub fn value(data: &'de [u8], value: &'v Value<'de>) -> Self {
+		Self {
+			data,
+			slot: Slot::Item(value),
+		}
+	}
+
+	fn span(&self) -> Span {
+		match self.slot {
+			Slot::Item(v) => v.span(),
+			Slot::Items([v, ..], _) => v.span(),
+			Slot::Items([], span) => span,
+		}
+	}
+
+	fn error(&self, msg: impl fmt::Display) -> Error {
+		<Error as de::Error>::custom(msg).at(self.data, self.span())
+	}
+
+	fn single(&self) -> Result<&'v Value<'de>, Error> {
+		match self.slot {
+			Slot::Item(v) | Slot::Items([v], _) => Ok(v),
+			Slot::Items([], _) => Err(self.error("expected a value")),
+			Slot::Items([_, v, ..], _) => {
+				Err(Self::value(self.data, v).error("expected a single value"))
+			}
+		}
+	}
+
+	fn text(&self) -> Result<&'v Text<'de>, Error> {
+		self.single()?
+			.as_text()
+			.ok_or_else(|| self.error("expected a symbol or string"))
+	}
+
+	/// The contents of a group.
+	fn contents(&self) -> Result<(&'v [Value<'de>], Span), Error> {
+		match self.slot {
+			Slot::Item(Value::List(l)) => Ok((&l.items, l.span)),
+			Slot::Item(Value::Atom(_)) => Err(self.error("expected a group")),
+			Slot::Items(items, span) => Ok((items, span)),
+		}
+	}
+
+	fn parse<T: FromStr>(&self, what: &str) -> Result<T, Error> {
+		let t = self.text()?;
+		t.text
+			.parse()
+			.map_err(|_| self.error(format_args!("expected {}, found {:?}", what, t.text)))
+	}
+
+	fn parse_int<T>(
+		&self,
+		what: &str,
+		from_str_radix: fn(&str, u32) -> Result<T, core::num::ParseIntError>,
+	) -> Result<T, Error> {
+		let t = self.text()?;
+		crate::decode::parse_int(&t.text, from_str_radix)
+			.ok_or_else(|| self.error(format_args!("expected {}, found {:?}", what, t.text)))
+	}
+
+	fn visit_text<V: Visitor<'de>>(&self, visitor: V) -> Result<V::Value, Error> {
+		match &self.text()?.text {
+			Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+			Cow::Owned(s) => visitor.visit_str(s),
+		}
+	}
+}
+
+macro_rules! int {
+	($($f:ident $v:ident $t:ident)*) => {
+		$(
+			fn $f<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+				visitor.$v(self.parse_int(stringify!($t), $t::from_str_radix)?)
+			}
+		)*
+	};
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'_, 'de> {
+	type Error = Error;
+
+	/// Symbols are booleans or numbers if they can be parsed as such.
+	/// Everything else is a string or a sequence.
+	fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		let t = match self.slot {
+			Slot::Item(Value::Atom(t)) | Slot::Items([Value::Atom(t)], _) => t,
+			_ => return self.deserialize_seq(visitor),
+		};
+		if t.quote.is_none() {
+			if let Ok(b) = t.text.parse() {
+				return visitor.visit_bool(b);
+			} else if let Ok(n) = self.parse_int("", u64::from_str_radix) {
+				return visitor.visit_u64(n);
+			} else if let Ok(n) = self.parse_int("", i64::from_str_radix) {
+				return visitor.visit_i64(n);
+			} else if t
+				.text
+				.starts_with(|c: char| c.is_ascii_digit() || "+-.".contains(c))
+			{
+				if let Ok(n) = t.text.parse() {
+					return visitor.visit_f64(n);
+				}
+			}
+		}
+		self.visit_text(visitor)
+	}
+
+	fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_bool(self.parse("a boolean")?)
+	}
+
+	int! {
+		deserialize_i8 visit_i8 i8
+		deserialize_i16 visit_i16 i16
+		deserialize_i32 visit_i32 i32
+		deserialize_i64 visit_i64 i64
+		deserialize_i128 visit_i128 i128
+		deserialize_u8 visit_u8 u8
+		deserialize_u16 visit_u16 u16
+		deserialize_u32 visit_u32 u32
+		deserialize_u64 visit_u64 u64
+		deserialize_u128 visit_u128 u128
+	}
+
+	fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_f32(self.parse("a number")?)
+	}
+
+	fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_f64(self.parse("a number")?)
+	}
+
+	fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_char(self.parse("a character")?)
+	}
+
+	fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		self.visit_text(visitor)
+	}
+
+	fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		self.visit_text(visitor)
+	}
+
+	fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		match &self.text()?.text {
+			Cow::Borrowed(s) => visitor.visit_borrowed_bytes(s.as_bytes()),
+			Cow::Owned(s) => visitor.visit_bytes(s.as_bytes()),
+		}
+	}
+
+	fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		self.deserialize_bytes(visitor)
+	}
+
+	fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		match self.slot {
+			Slot::Items([], _) => visitor.visit_none(),
+			Slot::Item(Value::List(l)) if l.is_empty() => visitor.visit_none(),
+			_ => visitor.visit_some(self),
+		}
+	}
+
+	fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		match self.contents()? {
+			([], _) => visitor.visit_unit(),
+			_ => Err(self.error("expected `()`")),
+		}
+	}
+
+	fn deserialize_unit_struct<V: Visitor<'de>>(
+		self,
+		_: &'static str,
+		visitor: V,
+	) -> Result<V::Value, Error> {
+		self.deserialize_unit(visitor)
+	}
+
+	fn deserialize_newtype_struct<V: Visitor<'de>>(
+		self,
+		_: &'static str,
+		visitor: V,
+	) -> Result<V::Value, Error> {
+		visitor.visit_newtype_struct(self)
+	}
+
+	fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		let (items, _) = self.contents()?;
+		visitor.visit_seq(SeqAccess {
+			data: self.data,
+			items: items.iter(),
+		})
+	}
+
+	fn deserialize_tuple<V: Visitor<'de>>(self, _: usize, visitor: V) -> Result<V::Value, Error> {
+		self.deserialize_seq(visitor)
+	}
+
+	fn deserialize_tuple_struct<V: Visitor<'de>>(
+		self,
+		_: &'static str,
+		_: usize,
+		visitor: V,
+	) -> Result<V::Value, Error> {
+		self.deserialize_seq(visitor)
+	}
+
+	fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		let (items, _) = self.contents()?;
+		visitor.visit_map(MapAccess {
+			data: self.data,
+			items: items.iter(),
+			value: None,
+		})
+	}
+
+	fn deserialize_struct<V: Visitor<'de>>(
+		self,
+		_: &'static str,
+		_: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, Error> {
+		self.deserialize_map(visitor)
+	}
+
+	fn deserialize_enum<V: Visitor<'de>>(
+		self,
+		_: &'static str,
+		_: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, Error> {
+		let (variant, rest) = match self.slot {
+			Slot::Items([variant, rest @ ..], span) if !rest.is_empty() => {
+				(variant, Slot::Items(rest, span))
+			}
+			_ => match self.single()? {
+				v @ Value::Atom(_) => (v, Slot::Items(&[], v.span())),
+				v @ Value::List(l) => match &l.items[..] {
+					[variant, rest @ ..] => (variant, Slot::Items(rest, l.span)),
+					[] => return Err(Self::value(self.data, v).error("expected an enum variant")),
+				},
+			},
+		};
+		if !variant.is_atom() {
+			return Err(Self::value(self.data, variant).error("expected an enum variant"));
+		}
+		visitor.visit_enum(EnumAccess {
+			variant: Self::value(self.data, variant),
+			rest: Self {
+				data: self.data,
+				slot: rest,
+			},
+		})
+	}
+
+	fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		self.visit_text(visitor)
+	}
+
+	fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_unit()
+	}
+}
+
+struct SeqAccess<'v, 'de> {
+	data: &'de [u8],
+	items: core::slice::Iter<'v, Value<'de>>,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'_, 'de> {
+	type Error = Error;
+
+	fn next_element_seed<T: DeserializeSeed<'de>>(
+		&mut self,
+		seed: T,
+	) -> Result<Option<T::Value>, Error> {
+		let Some(v) = self.items.next() else {
+			return Ok(None);
+		};
+		seed.deserialize(Deserializer::value(self.data, v))
+			.map(Some)
+			.map_err(|e| e.at(self.data, v.span()))
+	}
+
+	fn size_hint(&self) -> Option<usize> {
+		Some(self.items.len())
+	}
+}
+
+struct MapAccess<'v, 'de> {
+	data: &'de [u8],
+	items: core::slice::Iter<'v, Value<'de>>,
+	value: Option<&'v List<'de>>,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'_, 'de> {
+	type Error = Error;
+
+	fn next_key_seed<K: DeserializeSeed<'de>>(
+		&mut self,
+		seed: K,
+	) -> Result<Option<K::Value>, Error> {
+		let Some(v) = self.items.next() else {
+			return Ok(None);
+		};
+		let de = Deserializer::value(self.data, v);
+		let l = match v {
+			Value::List(l) if !l.is_empty() => l,
+			_ => return Err(de.error("expected a `(field value)` group")),
+		};
+		self.value = Some(l);
+		let key = &l.items[0];
+		seed.deserialize(Deserializer::value(self.data, key))
+			.map(Some)
+			.map_err(|e| e.at(self.data, key.span()))
+	}
+
+	fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+		let l = self
+			.value
+			.take()
+			.expect("next_value called before next_key");
+		let de = Deserializer {
+			data: self.data,
+			slot: Slot::Items(l.tail(), l.span),
+		};
+		seed.deserialize(de).map_err(|e| e.at(self.data, l.span))
+	}
+
+	fn size_hint(&self) -> Option<usize> {
+		Some(self.items.len())
+	}
+}
+
+struct EnumAccess<'v, 'de> {
+	variant: Deserializer<'v, 'de>,
+	rest: Deserializer<'v, 'de>,
+}
+
+impl<'v, 'de> de::EnumAccess<'de> for EnumAccess<'v, 'de> {
+	type Error = Error;
+	type Variant = Deserializer<'v, 'de>;
+
+	fn variant_seed<V: DeserializeSeed<'de>>(
+		self,
+		seed: V,
+	) -> Result<(V::Value, Self::Variant), Error> {
+		let variant = seed
+			.deserialize(self.variant)
+			.map_err(|e| e.at(self.variant.data, self.variant.span()))?;
+		Ok((variant, self.rest))
+	}
+}
+
+impl<'de> de::VariantAccess<'de> for Deserializer<'_, 'de> {
+	type Error = Error;
+
+	fn unit_variant(self) -> Result<(), Error> {
+		match self.slot {
+			Slot::Items([], _) => Ok(()),
+			_ => Err(self.error("unexpected value after unit variant")),
+		}
+	}
+
+	fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+		seed.deserialize(self)
+	}
+
+	fn tuple_variant<V: Visitor<'de>>(self, _: usize, visitor: V) -> Result<V::Value, Error> {
+		de::Deserializer::deserialize_seq(self, visitor)
+	}
+
+	fn struct_variant<V: Visitor<'de>>(
+		self,
+		_: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, Error> {
+		de::Deserializer::deserialize_map(self, visitor)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use serde::Deserialize;
+	use std::collections::BTreeMap;
+
+	#[derive(Deserialize, Debug, PartialEq)]
+	enum Color {
+		Red,
+		Gray(u8),
+		Rgb(u8, u8, u8),
+		Named { name: String },
+	}
+
+	#[derive(Deserialize, Debug, PartialEq)]
+	struct Port(u16);
+
+	#[derive(Deserialize, Debug, PartialEq)]
+	struct Config<'a> {
+		name: &'a str,
+		escaped: String,
+		enabled: bool,
+		ratio: f32,
+		id: u16,
+		offset: i32,
+		letter: char,
+		ports: Vec<Port>,
+		pairs: Vec<(u8, &'a str)>,
+		nested: Nested,
+		colors: Vec<Color>,
+		inline: Color,
+		maybe: Option<u32>,
+		empty: Option<u32>,
+		missing: Option<u32>,
+		map: BTreeMap<&'a str, u32>,
+		unit: (),
+	}
+
+	#[derive(Deserialize, Debug, PartialEq)]
+	struct Nested {
+		a: u8,
+		#[serde(default)]
+		b: u8,
+	}
+
+	#[test]
+	fn config() {
+		let t = r#"
+(name "hd graphics")
+(escaped "a\tb")
+(enabled true)
+(ratio 0.5)
+(id 0x1af4)
+(offset -12)
+(letter 'x')
+(ports 80 443)
+(pairs (1 one) (2 "two"))
+(nested (a 1) (unknown field))
+(colors Red (Red) (Gray 3) (Rgb 1 2 3) (Named (name blue)))
+(inline Rgb 4 5 6)
+(maybe 5)
+(empty)
+(map (x 1) (y 2))
+(unit)
+"#;
+		let cfg: Config = from_str(t).unwrap();
+		assert_eq!(
+			cfg,
+			Config {
+				name: "hd graphics",
+				escaped: "a\tb".into(),
+				enabled: true,
+				ratio: 0.5,
+				id: 0x1af4,
+				offset: -12,
+				letter: 'x',
+				ports: vec![Port(80), Port(443)],
+				pairs: vec![(1, "one"), (2, "two")],
+				nested: Nested { a: 1, b: 0 },
+				colors: vec![
+					Color::Red,
+					Color::Red,
+					Color::Gray(3),
+					Color::Rgb(1, 2, 3),
+					Color::Named {
+						name: "blue".into()
+					},
+				],
+				inline: Color::Rgb(4, 5, 6),
+				maybe: Some(5),
+				empty: None,
+				missing: None,
+				map: [("x", 1), ("y", 2)].into_iter().collect(),
+				unit: (),
+			}
+		);
+		// Zero-copy
+		assert!(t.as_bytes().as_ptr_range().contains(&cfg.name.as_ptr()));
+	}
+
+	#[test]
+	fn pci() {
+		let t = r#"(pci-drivers
+	(1af4 ; Red Hat
+		(1000 "drivers/pci/virtio/net")
+		(1001 "drivers/pci/virtio/blk"))
+	(8086 ; Intel
+		(1616 "drivers/pci/intel/hd graphics")))"#;
+		#[derive(Deserialize)]
+		struct Pci<'a> {
+			#[serde(rename = "pci-drivers", borrow)]
+			drivers: BTreeMap<&'a str, BTreeMap<&'a str, &'a str>>,
+		}
+		let pci: Pci = from_str(t).unwrap();
+		assert_eq!(pci.drivers["1af4"]["1001"], "drivers/pci/virtio/blk");
+		assert_eq!(pci.drivers["8086"].len(), 1);
+	}
+
+	#[test]
+	fn errors() {
+		#[derive(Deserialize, Debug)]
+		#[allow(dead_code)]
+		struct S {
+			a: u8,
+			b: Vec<u8>,
+		}
+		let e = from_str::<S>("(a 1)\n(b 1 2 x)").unwrap_err();
+		assert_eq!(e.line_col(), Some(LineCol { line: 2, column: 8 }));
+		assert_eq!(e.to_string(), "2:8: expected u8, found \"x\"");
+
+		let e = from_str::<S>("(a 1 2)").unwrap_err();
+		assert_eq!(e.offset(), Some(5));
+		assert_eq!(e.message(), "expected a single value");
+
+		let e = from_str::<S>("(a 1)").unwrap_err();
+		assert_eq!(e.message(), "missing field `b`");
+
+		let e = from_str::<S>("(a 1) x").unwrap_err();
+		assert_eq!(e.offset(), Some(6));
+
+		let e = from_str::<S>("(a 1").unwrap_err();
+		assert_eq!(e.message(), "unclosed group");
+		assert_eq!(e.offset(), Some(0));
+	}
+}
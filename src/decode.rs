@@ -0,0 +1,703 @@
+//! Decode values directly from a [`GroupsIter`] without allocating.
+//!
+//! The conventions are the same as those of [`de`](crate::de):
+//!
+//! - Booleans, numbers, characters and strings are single symbols or strings.
+//!   Integers may have a `0x`, `0o` or `0b` prefix.
+//! - Structs are a `(field value)` group per field.
+//!   The value of a field is everything after its head.
+//!   Unknown fields are ignored.
+//! - Options are `None` if there are no items, i.e. `(field)` or a missing field.
+//!   As a single item `()` is `None`.
+//! - Enum variants are the head symbol of a group: `(Rgb 1 2 3)`
+//!   or `(Point (x 1) (y 2))`. Unit variants may be a bare symbol: `Red`.
+//!   In a sequence of items the group may be left out: `(color Rgb 1 2 3)`.
+//! - Newtype structs are transparent.
+//!
+//! Implementations for structs and enums can be derived with `#[derive(FromScf)]`
+//! if the `derive` feature is enabled. The derive accepts these attributes:
+//!
+//! - `#[scf(rename = "name")]` on fields and variants uses a different head symbol.
+//! - `#[scf(default)]` on a field uses [`Default::default`] if the field is missing.
+//! - `#[scf(flatten)]` on a field decodes it from the same groups as the struct itself.
+//!
+//! ```
+//! # #[cfg(feature = "derive")]
+//! # {
+//! use scf::FromScf;
+//!
+//! #[derive(FromScf, Debug, PartialEq)]
+//! enum Bus {
+//!     #[scf(rename = "pci")]
+//!     Pci { vendor: u16, device: u16 },
+//!     #[scf(rename = "usb")]
+//!     Usb(u8, u8),
+//! }
+//!
+//! #[derive(FromScf, Debug, PartialEq)]
+//! struct Driver<'a> {
+//!     bus: Bus,
+//!     path: &'a str,
+//!     #[scf(default)]
+//!     priority: u8,
+//! }
+//!
+//! let driver: Driver = scf::decode(br#"
+//! (bus pci (vendor 0x1af4) (device 0x1000))
+//! (path "drivers/pci/virtio/net")
+//! "#).unwrap();
+//! assert_eq!(driver.bus, Bus::Pci { vendor: 0x1af4, device: 0x1000 });
+//! assert_eq!(driver.priority, 0);
+//! # }
+//! ```
+
+use crate::{Atom, Error, ErrorKind, GroupsIter, Item, LineCol, Span};
+use core::{fmt, num::ParseIntError, str};
+
+/// A type that can be decoded from items.
+///
+/// Implementors must override at least one of [`FromScf::from_items`]
+/// and [`FromScf::from_item`].
+///
+/// Errors returned by [`FromScf::from_item`] may lack [`DecodeError::line_col`]
+/// as an [`Item`] has no access to the data. It is filled in by the caller.
+pub trait FromScf<'a>: Sized {
+	/// Decode from all remaining items of a group.
+	///
+	/// By default this expects exactly one item and passes it to [`FromScf::from_item`].
+	fn from_items(it: &mut GroupsIter<'a, '_>) -> Result<Self, DecodeError> {
+		let v = next(it)?;
+		end(it)?;
+		Ok(v)
+	}
+
+	/// Decode from a single item.
+	///
+	/// By default this expects a group and passes its contents to [`FromScf::from_items`].
+	fn from_item(item: Item<'a, '_>) -> Result<Self, DecodeError> {
+		match item {
+			Item::Group(mut g) => Self::from_items(&mut g),
+			Item::Str(a) => Err(DecodeError::expected("a group", a.span)),
+		}
+	}
+
+	/// The value to use for a missing field, if any.
+	fn missing() -> Option<Self> {
+		None
+	}
+}
+
+/// Decode a value from the top level of a document.
+pub fn decode<'a, T: FromScf<'a>>(data: &'a [u8]) -> Result<T, DecodeError> {
+	let mut cf = crate::parse2(data);
+	let r = T::from_items(&mut cf.iter());
+	match cf.into_error() {
+		Some(e) => Err(e.into()),
+		None => r.map_err(|e| e.locate(data)),
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DecodeErrorKind {
+	/// The document is malformed.
+	Syntax(ErrorKind),
+	/// An item has the wrong type or could not be parsed.
+	Expected(&'static str),
+	MissingField,
+	DuplicateField,
+	UnknownVariant,
+	/// There are more items than expected.
+	TrailingItem,
+}
+
+impl fmt::Display for DecodeErrorKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Syntax(k) => write!(f, "{}, expected {}", k, k.expected()),
+			Self::Expected(what) => write!(f, "expected {}", what),
+			Self::MissingField => f.write_str("missing field"),
+			Self::DuplicateField => f.write_str("duplicate field"),
+			Self::UnknownVariant => f.write_str("unknown variant"),
+			Self::TrailingItem => f.write_str("unexpected item"),
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DecodeError {
+	pub kind: DecodeErrorKind,
+	/// The innermost field that failed to decode.
+	pub field: Option<&'static str>,
+	/// The location of the offending item.
+	pub span: Span,
+	/// The line and column of the start of [`DecodeError::span`],
+	/// if the data was available when the error was created.
+	pub line_col: Option<LineCol>,
+}
+
+impl DecodeError {
+	pub fn new(kind: DecodeErrorKind, span: Span) -> Self {
+		Self {
+			kind,
+			field: None,
+			span,
+			line_col: None,
+		}
+	}
+
+	pub fn expected(what: &'static str, span: Span) -> Self {
+		Self::new(DecodeErrorKind::Expected(what), span)
+	}
+
+	/// Set the field the error occurred in, unless already set.
+	pub fn in_field(mut self, field: &'static str) -> Self {
+		self.field.get_or_insert(field);
+		self
+	}
+
+	/// Set the line and column from the data, unless already set.
+	pub fn locate(mut self, data: &[u8]) -> Self {
+		self.line_col
+			.get_or_insert_with(|| crate::line_col(data, self.span.start));
+		self
+	}
+}
+
+impl From<Error> for DecodeError {
+	fn from(e: Error) -> Self {
+		Self {
+			kind: DecodeErrorKind::Syntax(e.kind),
+			field: None,
+			span: Span {
+				start: e.offset,
+				end: e.offset,
+			},
+			line_col: Some(e.line_col),
+		}
+	}
+}
+
+impl fmt::Display for DecodeError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if let Some(lc) = self.line_col {
+			write!(f, "{}: ", lc)?;
+		}
+		self.kind.fmt(f)?;
+		match (self.kind, self.field) {
+			(DecodeErrorKind::MissingField | DecodeErrorKind::DuplicateField, Some(name)) => {
+				write!(f, " `{}`", name)
+			}
+			(_, Some(name)) => write!(f, " in field `{}`", name),
+			(_, None) => Ok(()),
+		}
+	}
+}
+
+impl core::error::Error for DecodeError {}
+
+/// Return the error that stopped iteration, if any.
+pub fn check(it: &GroupsIter<'_, '_>) -> Result<(), DecodeError> {
+	it.error().map_or(Ok(()), |e| Err(e.into()))
+}
+
+/// Decode the next item.
+pub fn next<'a, T: FromScf<'a>>(it: &mut GroupsIter<'a, '_>) -> Result<T, DecodeError> {
+	match it.next() {
+		Some(item) => T::from_item(item).map_err(|e| e.locate(it.data())),
+		None => {
+			check(it)?;
+			let span = it.span();
+			let end = span.end.saturating_sub(usize::from(span.start != span.end));
+			Err(DecodeError::expected("a value", Span { start: end, end }).locate(it.data()))
+		}
+	}
+}
+
+/// Check that there are no items left.
+pub fn end(it: &mut GroupsIter<'_, '_>) -> Result<(), DecodeError> {
+	match it.next() {
+		Some(item) => {
+			let e = DecodeError::new(DecodeErrorKind::TrailingItem, item.span());
+			Err(e.locate(it.data()))
+		}
+		None => check(it),
+	}
+}
+
+/// Parse an integer with an optional `0x`, `0o` or `0b` prefix after the sign.
+pub(crate) fn parse_int<T>(
+	s: &str,
+	from_str_radix: fn(&str, u32) -> Result<T, ParseIntError>,
+) -> Option<T> {
+	let (neg, rest) = s.strip_prefix('-').map_or((false, s), |s| (true, s));
+	let Some((radix, digits)) = [("0x", 16), ("0o", 8), ("0b", 2)]
+		.iter()
+		.find_map(|&(p, r)| rest.strip_prefix(p).map(|s| (r, s)))
+	else {
+		return from_str_radix(s, 10).ok();
+	};
+	// `from_str_radix` would accept another sign after the prefix.
+	if digits.starts_with(['+', '-']) {
+		return None;
+	}
+	if !neg {
+		return from_str_radix(digits, radix).ok();
+	}
+	// Enough for any 128-bit integer in binary.
+	let mut buf = [0; 130];
+	let buf = buf.get_mut(..digits.len() + 1)?;
+	buf[0] = b'-';
+	buf[1..].copy_from_slice(digits.as_bytes());
+	from_str_radix(str::from_utf8(buf).ok()?, radix).ok()
+}
+
+fn atom<'a>(item: Item<'a, '_>, what: &'static str) -> Result<Atom<'a>, DecodeError> {
+	match item {
+		Item::Str(a) => Ok(a),
+		Item::Group(mut g) => {
+			// Read the whole group so the span is complete.
+			for _ in &mut g {}
+			Err(DecodeError::expected(what, g.span()))
+		}
+	}
+}
+
+macro_rules! int {
+	($($t:ident)*) => {
+		$(
+			impl<'a> FromScf<'a> for $t {
+				fn from_item(item: Item<'a, '_>) -> Result<Self, DecodeError> {
+					let a = atom(item, concat!("a ", stringify!($t)))?;
+					parse_int(a.text, $t::from_str_radix)
+						.ok_or(DecodeError::expected(concat!("a ", stringify!($t)), a.span))
+				}
+			}
+		)*
+	};
+}
+
+int!(u8 u16 u32 u64 u128 usize i8 i16 i32 i64 i128 isize);
+
+macro_rules! parse {
+	($($t:ident $what:literal)*) => {
+		$(
+			impl<'a> FromScf<'a> for $t {
+				fn from_item(item: Item<'a, '_>) -> Result<Self, DecodeError> {
+					let a = atom(item, $what)?;
+					a.text.parse().map_err(|_| DecodeError::expected($what, a.span))
+				}
+			}
+		)*
+	};
+}
+
+parse!(bool "a boolean" f32 "an f32" f64 "an f64");
+
+impl<'a> FromScf<'a> for char {
+	fn from_item(item: Item<'a, '_>) -> Result<Self, DecodeError> {
+		let a = atom(item, "a character")?;
+		let mut buf = [0; 16];
+		let s = match buf.get_mut(..a.text.len()) {
+			Some(buf) => a.unescape_into(buf).ok(),
+			None => None,
+		};
+		let mut chars = s.unwrap_or_default().chars();
+		match (chars.next(), chars.next()) {
+			(Some(c), None) => Ok(c),
+			_ => Err(DecodeError::expected("a character", a.span)),
+		}
+	}
+}
+
+/// Strings with escape sequences can't be borrowed and are rejected.
+impl<'a> FromScf<'a> for &'a str {
+	fn from_item(item: Item<'a, '_>) -> Result<Self, DecodeError> {
+		let a = atom(item, "a string")?;
+		if a.is_quoted() && a.text.contains('\\') {
+			return Err(DecodeError::expected(
+				"a string without escape sequences",
+				a.span,
+			));
+		}
+		Ok(a.text)
+	}
+}
+
+impl<'a> FromScf<'a> for Atom<'a> {
+	fn from_item(item: Item<'a, '_>) -> Result<Self, DecodeError> {
+		atom(item, "a symbol or string")
+	}
+}
+
+impl<'a> FromScf<'a> for () {
+	fn from_items(it: &mut GroupsIter<'a, '_>) -> Result<Self, DecodeError> {
+		end(it)
+	}
+}
+
+impl<'a, T: FromScf<'a>> FromScf<'a> for Option<T> {
+	fn from_items(it: &mut GroupsIter<'a, '_>) -> Result<Self, DecodeError> {
+		if it.at_end() {
+			return end(it).map(|()| None);
+		}
+		T::from_items(it).map(Some)
+	}
+
+	fn from_item(item: Item<'a, '_>) -> Result<Self, DecodeError> {
+		match item {
+			Item::Group(mut g) if g.at_end() => end(&mut g).map(|()| None),
+			item => T::from_item(item).map(Some),
+		}
+	}
+
+	fn missing() -> Option<Self> {
+		Some(None)
+	}
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> FromScf<'a> for alloc::string::String {
+	fn from_item(item: Item<'a, '_>) -> Result<Self, DecodeError> {
+		let a = atom(item, "a string")?;
+		match a.unescape() {
+			Ok(s) => Ok(s.into_owned()),
+			Err(e) => {
+				let start = a.span.start + 1 + e.offset;
+				let e =
+					DecodeError::new(DecodeErrorKind::Syntax(e.kind), Span { start, end: start });
+				Err(e)
+			}
+		}
+	}
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, T: FromScf<'a>> FromScf<'a> for alloc::vec::Vec<T> {
+	fn from_items(it: &mut GroupsIter<'a, '_>) -> Result<Self, DecodeError> {
+		let mut v = alloc::vec::Vec::new();
+		while !it.at_end() {
+			v.push(next(it)?);
+		}
+		check(it)?;
+		Ok(v)
+	}
+}
+
+/// Integers that can be parsed in a given radix.
+pub trait FromStrRadix: Sized {
+	fn from_str_radix(s: &str, radix: u32) -> Result<Self, ParseIntError>;
+}
+
+macro_rules! accessors {
+	($($t:ident $next:ident $into:ident)*) => {
+		$(
+			impl FromStrRadix for $t {
+				fn from_str_radix(s: &str, radix: u32) -> Result<Self, ParseIntError> {
+					$t::from_str_radix(s, radix)
+				}
+			}
+		)*
+
+		impl<'a> GroupsIter<'a, '_> {
+			$(
+				#[doc = concat!("Decode the next item as a `", stringify!($t), "`.")]
+				pub fn $next(&mut self) -> Result<$t, DecodeError> {
+					self.next_value()
+				}
+			)*
+		}
+
+		impl<'a> Item<'a, '_> {
+			$(
+				#[doc = concat!("Decode this item as a `", stringify!($t), "`.")]
+				pub fn $into(self) -> Result<$t, DecodeError> {
+					self.into_value()
+				}
+			)*
+		}
+	};
+}
+
+accessors! {
+	u8 next_u8 into_u8
+	u16 next_u16 into_u16
+	u32 next_u32 into_u32
+	u64 next_u64 into_u64
+	u128 next_u128 into_u128
+	usize next_usize into_usize
+	i8 next_i8 into_i8
+	i16 next_i16 into_i16
+	i32 next_i32 into_i32
+	i64 next_i64 into_i64
+	i128 next_i128 into_i128
+	isize next_isize into_isize
+}
+
+impl<'a> GroupsIter<'a, '_> {
+	/// Decode the next item.
+	///
+	/// Returns an error if there are no items left.
+	pub fn next_value<T: FromScf<'a>>(&mut self) -> Result<T, DecodeError> {
+		next(self)
+	}
+
+	/// Decode the next item as an integer in the given radix, without prefix.
+	///
+	/// # Panics
+	///
+	/// If `radix` is not in the range `2..=36`.
+	pub fn next_int_radix<T: FromStrRadix>(&mut self, radix: u32) -> Result<T, DecodeError> {
+		let a = next::<Atom>(self)?;
+		int_radix(a, radix).map_err(|e| e.locate(self.data()))
+	}
+
+	pub fn next_bool(&mut self) -> Result<bool, DecodeError> {
+		self.next_value()
+	}
+
+	pub fn next_f32(&mut self) -> Result<f32, DecodeError> {
+		self.next_value()
+	}
+
+	pub fn next_f64(&mut self) -> Result<f64, DecodeError> {
+		self.next_value()
+	}
+
+	/// Parse the next item with [`FromStr`](core::str::FromStr).
+	///
+	/// Escape sequences in quoted strings are not decoded.
+	pub fn next_parse<T: str::FromStr>(&mut self) -> Result<T, DecodeError> {
+		let a = next::<Atom>(self)?;
+		parse(a).map_err(|e| e.locate(self.data()))
+	}
+}
+
+/// The errors of these methods lack [`DecodeError::line_col`].
+/// Use [`DecodeError::locate`] to fill it in.
+impl<'a> Item<'a, '_> {
+	pub fn into_value<T: FromScf<'a>>(self) -> Result<T, DecodeError> {
+		T::from_item(self)
+	}
+
+	/// Decode this item as an integer in the given radix, without prefix.
+	///
+	/// # Panics
+	///
+	/// If `radix` is not in the range `2..=36`.
+	pub fn into_int_radix<T: FromStrRadix>(self, radix: u32) -> Result<T, DecodeError> {
+		int_radix(atom(self, "an integer")?, radix)
+	}
+
+	pub fn into_bool(self) -> Result<bool, DecodeError> {
+		self.into_value()
+	}
+
+	pub fn into_f32(self) -> Result<f32, DecodeError> {
+		self.into_value()
+	}
+
+	pub fn into_f64(self) -> Result<f64, DecodeError> {
+		self.into_value()
+	}
+
+	/// Parse this item with [`FromStr`](core::str::FromStr).
+	///
+	/// Escape sequences in quoted strings are not decoded.
+	pub fn into_parse<T: str::FromStr>(self) -> Result<T, DecodeError> {
+		parse(atom(self, core::any::type_name::<T>())?)
+	}
+}
+
+fn int_radix<T: FromStrRadix>(a: Atom<'_>, radix: u32) -> Result<T, DecodeError> {
+	T::from_str_radix(a.text, radix).map_err(|_| DecodeError::expected("an integer", a.span))
+}
+
+/// The error names the type as given by [`core::any::type_name`].
+fn parse<T: str::FromStr>(a: Atom<'_>) -> Result<T, DecodeError> {
+	a.text
+		.parse()
+		.map_err(|_| DecodeError::expected(core::any::type_name::<T>(), a.span))
+}
+
+/// Helpers for the derive macro.
+#[doc(hidden)]
+pub mod __private {
+	use super::*;
+
+	/// Split a `(field value)` group into its head and contents.
+	pub fn field<'a, 'b>(
+		it: &GroupsIter<'a, '_>,
+		item: Item<'a, 'b>,
+	) -> Result<(Atom<'a>, GroupsIter<'a, 'b>), DecodeError> {
+		let span = item.span();
+		let head = match item {
+			Item::Group(mut g) => match g.next() {
+				Some(Item::Str(head)) => {
+					// Let `flatten` rewind to the first field rather than the head.
+					g.start = None;
+					Some((head, g))
+				}
+				_ => None,
+			},
+			Item::Str(_) => None,
+		};
+		check(it)?;
+		head.ok_or(DecodeError::expected("a (field value) group", span).locate(it.data()))
+	}
+
+	pub fn set<'a, T: FromScf<'a>>(
+		slot: &mut Option<T>,
+		name: &'static str,
+		head: Atom<'a>,
+		g: &mut GroupsIter<'a, '_>,
+	) -> Result<(), DecodeError> {
+		if slot.is_some() {
+			let e = DecodeError::new(DecodeErrorKind::DuplicateField, head.span);
+			return Err(e.in_field(name).locate(g.data()));
+		}
+		let v = T::from_items(g).map_err(|e| e.in_field(name).locate(g.data()))?;
+		*slot = Some(v);
+		Ok(())
+	}
+
+	pub fn required<'a, T: FromScf<'a>>(
+		slot: Option<T>,
+		name: &'static str,
+		it: &GroupsIter<'a, '_>,
+	) -> Result<T, DecodeError> {
+		slot.or_else(T::missing).ok_or_else(|| {
+			let e = DecodeError::new(DecodeErrorKind::MissingField, it.span());
+			e.in_field(name).locate(it.data())
+		})
+	}
+
+	pub fn flatten<'a, T: FromScf<'a>>(
+		name: &'static str,
+		it: &mut GroupsIter<'a, '_>,
+	) -> Result<T, DecodeError> {
+		it.rewind();
+		T::from_items(it).map_err(|e| e.in_field(name).locate(it.data()))
+	}
+
+	pub fn unknown_variant(head: Atom<'_>) -> DecodeError {
+		DecodeError::new(DecodeErrorKind::UnknownVariant, head.span)
+	}
+
+	pub fn variant<'a, 'b>(it: &mut GroupsIter<'a, 'b>) -> Result<Item<'a, 'b>, DecodeError> {
+		match it.next() {
+			Some(item) => Ok(item),
+			None => {
+				check(it)?;
+				let e = DecodeError::expected("a variant", it.span());
+				Err(e.locate(it.data()))
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn primitives() {
+		assert_eq!(decode::<u16>(b"0x1af4"), Ok(0x1af4));
+		assert_eq!(decode::<i8>(b"-0x80"), Ok(-128));
+		assert!(decode::<u16>(b"0x+5").is_err());
+		assert!(decode::<i16>(b"0x-5").is_err());
+		assert!(decode::<i16>(b"-0x+5").is_err());
+		assert!(decode::<i16>(b"-0x-5").is_err());
+		assert_eq!(decode::<i64>(b"-12"), Ok(-12));
+		assert_eq!(decode::<bool>(b"true"), Ok(true));
+		assert_eq!(decode::<f64>(b"1.5"), Ok(1.5));
+		assert_eq!(decode::<char>(br#""\n""#), Ok('\n'));
+		assert_eq!(decode::<&str>(br#""a b""#), Ok("a b"));
+		assert_eq!(decode::<Option<u8>>(b""), Ok(None));
+		assert_eq!(decode::<Option<u8>>(b"5"), Ok(Some(5)));
+		assert_eq!(decode::<()>(b""), Ok(()));
+	}
+
+	#[cfg(feature = "alloc")]
+	#[test]
+	fn alloc() {
+		use alloc::{string::String, vec, vec::Vec};
+		assert_eq!(decode::<String>(br#""a\tb""#).as_deref(), Ok("a\tb"));
+		assert_eq!(decode::<Vec<u8>>(b"1 2 3"), Ok(vec![1, 2, 3]));
+		assert_eq!(
+			decode::<Vec<Vec<u8>>>(b"(1) () (2 3)"),
+			Ok(vec![vec![1], vec![], vec![2, 3]])
+		);
+		assert_eq!(
+			decode::<Vec<Option<u8>>>(b"1 () 2"),
+			Ok(vec![Some(1), None, Some(2)])
+		);
+	}
+
+	#[test]
+	fn errors() {
+		let e = decode::<u8>(b"\n 256").unwrap_err();
+		assert_eq!(e.kind, DecodeErrorKind::Expected("a u8"));
+		assert_eq!(e.span, Span { start: 2, end: 5 });
+		assert_eq!(e.to_string(), "2:2: expected a u8");
+		let e = decode::<u8>(b"1 2").unwrap_err();
+		assert_eq!(e.kind, DecodeErrorKind::TrailingItem);
+		assert_eq!(e.span, Span { start: 2, end: 3 });
+		let e = decode::<u8>(b"").unwrap_err();
+		assert_eq!(e.to_string(), "1:1: expected a value");
+		let e = decode::<&str>(br#""a\nb""#).unwrap_err();
+		assert_eq!(
+			e.kind,
+			DecodeErrorKind::Expected("a string without escape sequences")
+		);
+		let e = decode::<u8>(b"(1").unwrap_err();
+		assert_eq!(e.kind, DecodeErrorKind::Syntax(ErrorKind::UnclosedGroup));
+	}
+
+	#[test]
+	fn accessors() {
+		let t = br#"(pci-drivers
+	(1af4 ; Red Hat
+		(1000 "drivers/pci/virtio/net")
+		(1001 "drivers/pci/virtio/blk"))
+	(8086 ; Intel
+		(1616 "drivers/pci/intel/hd_graphics" true 1.5 -7)))"#;
+		let mut cf = crate::parse2(t);
+		{
+			let mut it = cf.iter();
+			let mut drivers = it.next_group().unwrap();
+			assert_eq!(drivers.next_str(), Some("pci-drivers"));
+			let mut vendor = drivers.next_group().unwrap();
+			assert_eq!(vendor.next_int_radix::<u16>(16), Ok(0x1af4));
+			let mut dev = vendor.next_group().unwrap();
+			assert_eq!(dev.next_int_radix::<u16>(16), Ok(0x1000));
+			assert_eq!(dev.next_value(), Ok("drivers/pci/virtio/net"));
+			let e = dev.next_u32().unwrap_err();
+			assert_eq!(e.kind, DecodeErrorKind::Expected("a value"));
+			assert_eq!(e.to_string(), "3:33: expected a value");
+			drop(dev);
+			let mut dev = vendor.next_group().unwrap();
+			let e = dev.next_u8().unwrap_err();
+			assert_eq!(e.to_string(), "4:4: expected a u8");
+			drop((dev, vendor));
+			let mut vendor = drivers.next_group().unwrap();
+			assert_eq!(vendor.next_u16(), Ok(8086));
+			let mut dev = vendor.next_group().unwrap();
+			assert_eq!(dev.next().unwrap().into_int_radix::<u16>(16), Ok(0x1616));
+			let e = dev.next_int_radix::<u16>(16).unwrap_err();
+			assert_eq!(e.to_string(), "6:9: expected an integer");
+			assert_eq!(dev.next_bool(), Ok(true));
+			assert_eq!(dev.next_f64(), Ok(1.5));
+			assert_eq!(dev.next_parse::<i8>(), Ok(-7));
+			assert!(dev.at_end());
+		}
+		assert!(cf.into_error().is_none());
+
+		let mut cf = crate::parse2(b"(x) 1.5.2");
+		let mut it = cf.iter();
+		let e = it.next().unwrap().into_f32().unwrap_err();
+		assert_eq!(e.kind, DecodeErrorKind::Expected("an f32"));
+		assert_eq!((e.span, e.line_col), (Span { start: 0, end: 3 }, None));
+		let e = it.next_parse::<f64>().unwrap_err();
+		assert_eq!(e.to_string(), "1:5: expected f64");
+	}
+}
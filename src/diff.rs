@@ -0,0 +1,242 @@
+//! Compare documents by structure, ignoring whitespace and comments.
+//!
+//! Groups are matched by their head: the first group with a given head in the old document
+//! is compared with the first group with that head in the new document, and so on.
+//! Reordering groups with different heads is therefore not a change.
+//! Other items, i.e. symbols, strings and groups without a head, are compared in order.
+//!
+//! ```
+//! use scf::{diff, Document};
+//!
+//! let old = Document::parse(b"(pci-drivers (1000 net) (1001 blk))").unwrap();
+//! let new = Document::parse(b"(pci-drivers\n (1001 blk) ; reordered\n (1000 net-legacy))").unwrap();
+//! let changes = diff::diff(&old, &new);
+//! assert_eq!(changes.len(), 1);
+//! assert_eq!(changes[0].to_string(), "~ pci-drivers/1000: (1000 net) -> (1000 net-legacy)");
+//! ```
+
+use crate::{List, Value};
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::fmt;
+
+/// A step in the path of a change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Step<'d> {
+	/// The head of the group.
+	pub head: &'d str,
+	/// The amount of groups with the same head before it.
+	pub index: usize,
+}
+
+/// The head, followed by the index in brackets if it isn't 0.
+impl fmt::Display for Step<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(self.head)?;
+		if self.index > 0 {
+			write!(f, "[{}]", self.index)?;
+		}
+		Ok(())
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+	/// The group only exists in the new document.
+	Added,
+	/// The group only exists in the old document.
+	Removed,
+	/// The items of the group other than groups with a head differ.
+	///
+	/// Changes to groups inside it are reported separately.
+	Changed,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change<'d, 'a> {
+	pub kind: ChangeKind,
+	/// The path of the group. It is empty for changes at the top level of the document.
+	pub path: Vec<Step<'d>>,
+	/// The group in the old document, unless it was added.
+	pub old: Option<&'d List<'a>>,
+	/// The group in the new document, unless it was removed.
+	pub new: Option<&'d List<'a>>,
+}
+
+/// A line starting with `+`, `-` or `~`, followed by the path and the group.
+impl fmt::Display for Change<'_, '_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self.kind {
+			ChangeKind::Added => "+ ",
+			ChangeKind::Removed => "- ",
+			ChangeKind::Changed => "~ ",
+		})?;
+		for (i, s) in self.path.iter().enumerate() {
+			if i > 0 {
+				f.write_str("/")?;
+			}
+			s.fmt(f)?;
+		}
+		match (self.old, self.new) {
+			(Some(old), Some(new)) => write!(f, ": {} -> {}", old, new),
+			(Some(l), None) | (None, Some(l)) => write!(f, ": {}", l),
+			(None, None) => Ok(()),
+		}
+	}
+}
+
+/// The changes from `old` to `new`, in the order of `old`,
+/// followed by added groups in the order of `new`.
+///
+/// Pass a [`Document`](crate::Document) to compare its top-level items.
+pub fn diff<'d, 'a>(old: &'d List<'a>, new: &'d List<'a>) -> Vec<Change<'d, 'a>> {
+	let mut out = Vec::new();
+	diff_items(&mut Vec::new(), old, new, 0, &mut out);
+	out
+}
+
+/// Compare the items of `old` and `new` after the first `start`.
+fn diff_items<'d, 'a>(
+	path: &mut Vec<Step<'d>>,
+	old: &'d List<'a>,
+	new: &'d List<'a>,
+	start: usize,
+	out: &mut Vec<Change<'d, 'a>>,
+) {
+	let (old_items, new_items) = (
+		old.items.get(start..).unwrap_or_default(),
+		new.items.get(start..).unwrap_or_default(),
+	);
+	let values = |items: &'d [Value<'a>]| items.iter().filter(|v| v.head().is_none());
+	if !values(old_items).eq(values(new_items)) {
+		out.push(Change {
+			kind: ChangeKind::Changed,
+			path: path.clone(),
+			old: Some(old),
+			new: Some(new),
+		});
+	}
+	let new_groups = groups(new_items);
+	let mut unmatched = new_groups.iter().copied().collect::<BTreeMap<_, _>>();
+	for (step, o) in groups(old_items) {
+		path.push(step);
+		match unmatched.remove(&step) {
+			Some(n) => diff_items(path, o, n, 1, out),
+			None => out.push(Change {
+				kind: ChangeKind::Removed,
+				path: path.clone(),
+				old: Some(o),
+				new: None,
+			}),
+		}
+		path.pop();
+	}
+	for (step, n) in new_groups
+		.into_iter()
+		.filter(|(s, _)| unmatched.contains_key(s))
+	{
+		path.push(step);
+		out.push(Change {
+			kind: ChangeKind::Added,
+			path: path.clone(),
+			old: None,
+			new: Some(n),
+		});
+		path.pop();
+	}
+}
+
+/// The groups with a head in `items` and their steps.
+fn groups<'d, 'a>(items: &'d [Value<'a>]) -> Vec<(Step<'d>, &'d List<'a>)> {
+	let mut counts = BTreeMap::new();
+	items
+		.iter()
+		.filter_map(|v| {
+			let head = v.head()?;
+			let count = counts.entry(head).or_insert(0);
+			let step = Step {
+				head,
+				index: *count,
+			};
+			*count += 1;
+			Some((step, v.as_list()?))
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{
+		fixture::{doc, PCI},
+		Document, Span,
+	};
+	use alloc::string::ToString;
+
+	#[test]
+	fn diff() {
+		let old = Document::parse(PCI).unwrap();
+		let new = doc(r#"(pci-drivers
+	(1af4
+		(1001 "drivers/pci/virtio/blk")
+		(1000 "drivers/pci/virtio/net")
+		(1040 "drivers/pci/virtio/gpu")
+		(1050 "drivers/pci/virtio/gpu-modern"))
+	(1af4 (1000 "drivers/pci/virtio/net-legacy")))"#);
+		let changes = super::diff(&old, &new);
+		let lines = changes.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+		assert_eq!(
+			lines,
+			[
+				"+ pci-drivers/1af4/1050: (1050 \"drivers/pci/virtio/gpu-modern\")",
+				"- pci-drivers/8086: (8086 (1616 \"drivers/pci/intel/hd_graphics\"))",
+				"+ pci-drivers/1af4[1]: (1af4 (1000 \"drivers/pci/virtio/net-legacy\"))",
+			]
+		);
+		assert_eq!(
+			changes[1].old.unwrap().span,
+			Span {
+				start: 134,
+				end: 189
+			}
+		);
+		assert_eq!(
+			changes[2].path,
+			[
+				Step {
+					head: "pci-drivers",
+					index: 0
+				},
+				Step {
+					head: "1af4",
+					index: 1
+				}
+			]
+		);
+		assert!(super::diff(&old, &old).is_empty());
+	}
+
+	#[test]
+	fn values() {
+		let old = Document::parse(b"top (a 1 (b) ()) (c)").unwrap();
+		let new = Document::parse(b"top ; comment\n(a 1 (b 2) ()) (c ())").unwrap();
+		let changes = super::diff(&old, &new);
+		let kinds = changes.iter().map(|c| c.kind).collect::<Vec<_>>();
+		assert_eq!(kinds, [ChangeKind::Changed, ChangeKind::Changed]);
+		assert_eq!(changes[0].to_string(), "~ a/b: (b) -> (b 2)");
+		assert_eq!(changes[1].to_string(), "~ c: (c) -> (c ())");
+
+		let new = Document::parse(b"(a 1 (b) ()) (c)").unwrap();
+		let changes = super::diff(&old, &new);
+		assert_eq!(changes.len(), 1);
+		assert!(changes[0].path.is_empty());
+		assert_eq!(changes[0].kind, ChangeKind::Changed);
+
+		let (old, new) = (doc("(modules a b) (x)"), doc("(x) (modules b a)"));
+		let changes = super::diff(&old, &new);
+		assert_eq!(changes.len(), 1);
+		assert_eq!(
+			changes[0].to_string(),
+			"~ modules: (modules a b) -> (modules b a)"
+		);
+	}
+}
@@ -0,0 +1,84 @@
+use crate::{Error, ErrorKind};
+
+/// Decode a single escape sequence. `s` starts right after the backslash.
+///
+/// Returns the decoded character and the amount of bytes consumed.
+pub(crate) fn decode(s: &[u8]) -> Option<(char, usize)> {
+	// `from_str_radix` also accepts a sign, so check the digits first.
+	let hex = |s: &[u8]| {
+		if s.is_empty() || !s.iter().all(u8::is_ascii_hexdigit) {
+			return None;
+		}
+		let s = core::str::from_utf8(s).ok()?;
+		u32::from_str_radix(s, 16).ok()
+	};
+	Some(match s.first()? {
+		b'\\' => ('\\', 1),
+		b'"' => ('"', 1),
+		b'\'' => ('\'', 1),
+		b'n' => ('\n', 1),
+		b't' => ('\t', 1),
+		b'r' => ('\r', 1),
+		b'0' => ('\0', 1),
+		b'x' => {
+			let n = hex(s.get(1..3)?)?;
+			// Anything above 0x7f would produce invalid UTF-8.
+			(n <= 0x7f).then_some((char::from_u32(n)?, 3))?
+		}
+		b'u' => {
+			if s.get(1) != Some(&b'{') {
+				return None;
+			}
+			let end = s.iter().take(9).position(|&c| c == b'}')?;
+			(char::from_u32(hex(s.get(2..end)?)?)?, end + 1)
+		}
+		_ => return None,
+	})
+}
+
+/// Decode the escape sequences in the contents of a quoted string into `buf`.
+///
+/// The following escapes are recognized: `\\`, `\"`, `\'`, `\n`, `\t`, `\r`, `\0`,
+/// `\xNN` (up to `\x7f`) and `\u{N}` (1 to 6 hex digits).
+///
+/// The decoded string is never longer than `s`,
+/// so a buffer of `s.len()` bytes is always large enough.
+///
+/// The position in the returned error is relative to `s`.
+///
+/// # Panics
+///
+/// If `buf` is too small to hold the decoded string.
+pub fn unescape_into<'b>(s: &str, buf: &'b mut [u8]) -> Result<&'b str, Error> {
+	let b = s.as_bytes();
+	let (mut i, mut k) = (0, 0);
+	let mut push = |bytes: &[u8]| {
+		buf[k..k + bytes.len()].copy_from_slice(bytes);
+		k += bytes.len();
+	};
+	while let Some(n) = b[i..].iter().position(|&c| c == b'\\') {
+		push(&b[i..i + n]);
+		i += n;
+		let (c, len) =
+			decode(&b[i + 1..]).ok_or_else(|| Error::new(b, ErrorKind::InvalidEscape, i))?;
+		push(c.encode_utf8(&mut [0; 4]).as_bytes());
+		i += 1 + len;
+	}
+	push(&b[i..]);
+	// We only copied whole UTF-8 sequences, so this can't fail.
+	Ok(core::str::from_utf8(&buf[..k]).expect("invalid UTF-8"))
+}
+
+/// Decode the escape sequences in the contents of a quoted string.
+///
+/// If there are no escape sequences `s` is returned as is.
+///
+/// See [`unescape_into`] for the recognized escapes.
+#[cfg(feature = "alloc")]
+pub fn unescape(s: &str) -> Result<alloc::borrow::Cow<'_, str>, Error> {
+	if !s.contains('\\') {
+		return Ok(s.into());
+	}
+	let mut buf = alloc::vec![0; s.len()];
+	Ok(alloc::string::String::from(unescape_into(s, &mut buf)?).into())
+}
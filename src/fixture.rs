@@ -0,0 +1,17 @@
+//! Documents shared by the tests.
+
+use crate::Document;
+
+/// The driver table from the README.
+pub(crate) const PCI: &[u8] = br#"(pci-drivers
+	(1af4 ; Red Hat
+		(1000 "drivers/pci/virtio/net")
+		(1001 "drivers/pci/virtio/blk")
+		(1040 "drivers/pci/virtio/gpu"))
+	(8086 ; Intel
+		(1616 "drivers/pci/intel/hd_graphics")))"#;
+
+#[track_caller]
+pub(crate) fn doc(s: &str) -> Document<'_> {
+	Document::parse(s.as_bytes()).unwrap()
+}
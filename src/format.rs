@@ -0,0 +1,362 @@
+use crate::{Error, Iter, Span, Token};
+use core::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FormatOptions {
+	/// The width after which groups are wrapped.
+	pub width: usize,
+	/// The width of a tab when measuring lines.
+	pub tab_width: usize,
+	/// See [`Groups::permissive`](crate::Groups::permissive).
+	pub permissive: bool,
+}
+
+impl Default for FormatOptions {
+	fn default() -> Self {
+		Self {
+			width: 80,
+			tab_width: 4,
+			permissive: false,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FormatError {
+	/// The input is not a valid document.
+	Parse(Error),
+	/// The underlying writer failed.
+	Fmt,
+}
+
+impl From<fmt::Error> for FormatError {
+	fn from(_: fmt::Error) -> Self {
+		Self::Fmt
+	}
+}
+
+impl fmt::Display for FormatError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Parse(e) => e.fmt(f),
+			Self::Fmt => f.write_str("failed to write"),
+		}
+	}
+}
+
+impl core::error::Error for FormatError {
+	fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+		match self {
+			Self::Parse(e) => Some(e),
+			Self::Fmt => None,
+		}
+	}
+}
+
+/// Format a document with the default options.
+///
+/// See [`format_with`].
+pub fn format<W: fmt::Write>(data: &[u8], out: &mut W) -> Result<(), FormatError> {
+	format_with(data, out, &FormatOptions::default())
+}
+
+/// Format a document.
+///
+/// Groups that fit within [`FormatOptions::width`] are put on a single line.
+/// Other groups keep their leading symbols on the line of the opening parenthesis
+/// and put every other item on its own line, indented with one tab per level.
+/// Closing parentheses are put right after the last item.
+///
+/// Comments are kept. A comment that shares a line with the preceding item stays on that line.
+/// Blank lines between items are collapsed to at most one.
+///
+/// The document is validated before anything is written.
+pub fn format_with<W: fmt::Write>(
+	data: &[u8],
+	out: &mut W,
+	options: &FormatOptions,
+) -> Result<(), FormatError> {
+	let mut cf = crate::parse2(data);
+	if options.permissive {
+		cf = cf.permissive();
+	}
+	for _ in cf.iter() {}
+	if let Some(e) = cf.into_error() {
+		return Err(FormatError::Parse(e));
+	}
+	let mut it = Iter::new(data);
+	it.permissive = options.permissive;
+	Printer {
+		data,
+		it,
+		out,
+		options,
+		col: 0,
+		prev: 0,
+	}
+	.top()?;
+	Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Line {
+	/// Nothing has been written yet.
+	Empty,
+	/// The current line has an item on it.
+	Item,
+	/// The current line ends with a comment.
+	Comment,
+}
+
+struct Printer<'a, 'o, W> {
+	data: &'a [u8],
+	it: Iter<'a>,
+	out: &'o mut W,
+	options: &'o FormatOptions,
+	col: usize,
+	/// The end of the last token that was printed.
+	prev: usize,
+}
+
+impl<'a, W: fmt::Write> Printer<'a, '_, W> {
+	fn next(&mut self) -> Option<(Token<'a>, Span)> {
+		// The document has already been validated.
+		self.it.next_spanned().and_then(Result::ok)
+	}
+
+	fn text(&self, span: Span) -> &'a str {
+		core::str::from_utf8(&self.data[span.start..span.end]).unwrap_or_default()
+	}
+
+	fn write(&mut self, s: &str) -> fmt::Result {
+		self.col += s.chars().count();
+		self.out.write_str(s)
+	}
+
+	fn line(&mut self, indent: usize, blank: bool, line: Line) -> fmt::Result {
+		if line != Line::Empty {
+			self.out.write_str(if blank { "\n\n" } else { "\n" })?;
+		}
+		for _ in 0..indent {
+			self.out.write_char('\t')?;
+		}
+		self.col = indent * self.options.tab_width;
+		Ok(())
+	}
+
+	/// Print the comments between the last token and `end`.
+	///
+	/// Returns the amount of newlines after the last comment.
+	fn trivia(
+		&mut self,
+		end: usize,
+		indent: usize,
+		line: &mut Line,
+		mut allow_blank: bool,
+	) -> Result<usize, fmt::Error> {
+		let (mut i, mut newlines) = (self.prev, 0);
+		while i < end {
+			match self.data[i] {
+				b'\n' => newlines += 1,
+				b';' => {
+					let len = self.data[i..end]
+						.iter()
+						.position(|&c| c == b'\n')
+						.unwrap_or(end - i);
+					if newlines == 0 && *line == Line::Item {
+						self.write(" ")?;
+					} else {
+						self.line(indent, newlines > 1 && allow_blank, *line)?;
+					}
+					let comment = self.data[i..i + len].trim_ascii_end();
+					for chunk in comment.utf8_chunks() {
+						self.write(chunk.valid())?;
+						if !chunk.invalid().is_empty() {
+							self.write("\u{fffd}")?;
+						}
+					}
+					*line = Line::Comment;
+					allow_blank = true;
+					newlines = 0;
+					i += len;
+					continue;
+				}
+				_ => {}
+			}
+			i += 1;
+		}
+		Ok(newlines)
+	}
+
+	fn top(&mut self) -> fmt::Result {
+		let mut line = Line::Empty;
+		let mut count = 0;
+		while let Some((tk, span)) = self.next() {
+			let newlines = self.trivia(span.start, 0, &mut line, count > 0)?;
+			self.line(0, newlines > 1, line)?;
+			self.item(tk, span, 0)?;
+			line = Line::Item;
+			count += 1;
+		}
+		self.trivia(self.data.len(), 0, &mut line, count > 0)?;
+		if line != Line::Empty {
+			self.out.write_char('\n')?;
+		}
+		Ok(())
+	}
+
+	/// Print an item. `indent` is the indentation of the line the item starts on.
+	fn item(&mut self, tk: Token<'a>, span: Span, indent: usize) -> fmt::Result {
+		self.prev = span.end;
+		match tk {
+			Token::Begin => self.group(indent),
+			_ => self.write(self.text(span)),
+		}
+	}
+
+	fn group(&mut self, indent: usize) -> fmt::Result {
+		if self
+			.flat_width()
+			.is_some_and(|w| self.col + w <= self.options.width)
+		{
+			return self.flat();
+		}
+		self.write("(")?;
+		let (mut line, mut count, mut wrapped) = (Line::Item, 0, false);
+		loop {
+			let Some((tk, span)) = self.next() else {
+				return Ok(());
+			};
+			let newlines = self.trivia(span.start, indent + 1, &mut line, count > 0)?;
+			if tk == Token::End {
+				if line == Line::Comment {
+					self.line(indent, false, line)?;
+				}
+				self.prev = span.end;
+				return self.write(")");
+			}
+			let fits = || self.col + 1 + self.text(span).chars().count() <= self.options.width;
+			if line == Line::Item && count == 0 {
+				// Right after the opening parenthesis.
+			} else if line == Line::Item && !wrapped && tk != Token::Begin && fits() {
+				self.write(" ")?;
+			} else {
+				let blank = newlines > 1 && (count > 0 || line == Line::Comment);
+				self.line(indent + 1, blank, line)?;
+				wrapped = true;
+			}
+			self.item(tk, span, indent + 1)?;
+			line = Line::Item;
+			count += 1;
+		}
+	}
+
+	/// The width of the group starting at the current position when put on a single line.
+	///
+	/// Returns `None` if the group contains comments or is too wide anyways.
+	fn flat_width(&self) -> Option<usize> {
+		let mut it = self.it.clone();
+		let (mut prev, mut width, mut depth, mut sep) = (self.prev, 1, 1, 0);
+		while depth > 0 {
+			let (tk, span) = it.next_spanned()?.ok()?;
+			if self.data[prev..span.start].contains(&b';') {
+				return None;
+			}
+			match tk {
+				Token::Begin => (width, depth, sep) = (width + sep + 1, depth + 1, 0),
+				Token::End => (width, depth, sep) = (width + 1, depth - 1, 1),
+				_ => (width, sep) = (width + sep + self.text(span).chars().count(), 1),
+			}
+			if width > self.options.width {
+				return None;
+			}
+			prev = span.end;
+		}
+		Some(width)
+	}
+
+	fn flat(&mut self) -> fmt::Result {
+		self.write("(")?;
+		let (mut depth, mut sep) = (1, false);
+		while depth > 0 {
+			let Some((tk, span)) = self.next() else {
+				break;
+			};
+			match tk {
+				Token::Begin => depth += 1,
+				Token::End => depth -= 1,
+				_ => {}
+			}
+			if sep && tk != Token::End {
+				self.write(" ")?;
+			}
+			self.write(self.text(span))?;
+			sep = tk != Token::Begin;
+			self.prev = span.end;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn fmt(s: &str, width: usize) -> String {
+		let mut out = String::new();
+		let options = FormatOptions {
+			width,
+			..Default::default()
+		};
+		format_with(s.as_bytes(), &mut out, &options).unwrap();
+		out
+	}
+
+	#[test]
+	fn readme() {
+		let expected = r#"(pci-drivers
+	(1af4 ; Red Hat
+		(1000 "drivers/pci/virtio/net")
+		(1001 "drivers/pci/virtio/blk")
+		(1040 "drivers/pci/virtio/gpu"))
+	(8086 ; Intel
+		(1616 "drivers/pci/intel/hd_graphics")))
+"#;
+		assert_eq!(fmt(expected, 80), expected);
+		let messy = r#"  (pci-drivers (1af4   ; Red Hat
+  (1000    "drivers/pci/virtio/net")
+(1001 "drivers/pci/virtio/blk") (1040
+	"drivers/pci/virtio/gpu")
+	)
+ (8086 ; Intel
+  (1616 "drivers/pci/intel/hd_graphics")
+ ))"#;
+		assert_eq!(fmt(messy, 80), expected);
+	}
+
+	#[test]
+	fn wrap() {
+		assert_eq!(fmt("(a b (c d) e)", 80), "(a b (c d) e)\n");
+		assert_eq!(fmt("(a b (c d) e)", 10), "(a b\n\t(c d)\n\te)\n");
+		assert_eq!(fmt("(a b (c d) e)", 3), "(a\n\tb\n\t(c\n\t\td)\n\te)\n");
+		assert_eq!(fmt("(abc 'x y' \"z\")", 9), "(abc\n\t'x y'\n\t\"z\")\n");
+	}
+
+	#[test]
+	fn comments() {
+		let t = "; header\n\n\n(a ; one\n\t; two\n\n\tb ; three\n) ; four\n(c)\n\n; five\n";
+		let expected = "; header\n\n(a ; one\n\t; two\n\n\tb ; three\n) ; four\n(c)\n\n; five\n";
+		assert_eq!(fmt(t, 80), expected);
+		assert_eq!(fmt(expected, 80), expected);
+		assert_eq!(fmt("", 80), "");
+		assert_eq!(fmt("a b", 80), "a\nb\n");
+	}
+
+	#[test]
+	fn invalid() {
+		let mut out = String::new();
+		let e = format(b"(a (b)", &mut out).unwrap_err();
+		assert!(matches!(e, FormatError::Parse(e) if e.kind == crate::ErrorKind::UnclosedGroup));
+		assert!(out.is_empty());
+	}
+}
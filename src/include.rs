@@ -0,0 +1,367 @@
+//! Expand `(include "path")` groups with the contents of other files.
+//!
+//! An include may appear anywhere in a document. It is replaced by
+//! the top-level items of the included file, which may include further files.
+//! The [`Origin`](crate::Origin) of every value is set to the file it was read from.
+//!
+//! ```
+//! use scf::include::{self, Resolver};
+//!
+//! struct Files;
+//!
+//! impl Resolver for Files {
+//!     type Error = &'static str;
+//!
+//!     fn resolve(&mut self, _from: Option<&str>, path: &str) -> Result<String, Self::Error> {
+//!         Ok(path.into())
+//!     }
+//!
+//!     fn load(&mut self, name: &str) -> Result<Vec<u8>, Self::Error> {
+//!         match name {
+//!             "pci.scf" => Ok(b"(pci-drivers (include intel.scf))".to_vec()),
+//!             "intel.scf" => Ok(b"(8086 (1616 \"drivers/pci/intel/hd_graphics\"))".to_vec()),
+//!             _ => Err("not found"),
+//!         }
+//!     }
+//! }
+//!
+//! let doc = include::load(&mut Files, "pci.scf").unwrap();
+//! assert_eq!(doc.lookup("pci-drivers/8086/1616").unwrap()[1].as_str(), Some("drivers/pci/intel/hd_graphics"));
+//! ```
+
+use crate::{Document, ErrorKind, LineCol, List, Value};
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+/// Locates and loads included files.
+pub trait Resolver {
+	type Error;
+
+	/// The name of the file that `path` refers to when included from the file `from`,
+	/// or `None` for the file passed to [`load`].
+	///
+	/// Every file must have exactly one name, as names are used to detect cycles.
+	fn resolve(&mut self, from: Option<&str>, path: &str) -> Result<String, Self::Error>;
+
+	/// The contents of the file with the given name.
+	fn load(&mut self, name: &str) -> Result<Vec<u8>, Self::Error>;
+}
+
+/// Resolves paths relative to the directory of the including file
+/// and loads files from the file system.
+///
+/// Names are canonical paths.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FsResolver;
+
+#[cfg(feature = "std")]
+impl Resolver for FsResolver {
+	type Error = std::io::Error;
+
+	fn resolve(&mut self, from: Option<&str>, path: &str) -> Result<String, Self::Error> {
+		let dir = from.and_then(|f| std::path::Path::new(f).parent());
+		let path = dir.map_or_else(|| path.into(), |d| d.join(path));
+		std::fs::canonicalize(path)?
+			.into_os_string()
+			.into_string()
+			.map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, "path is not UTF-8"))
+	}
+
+	fn load(&mut self, name: &str) -> Result<Vec<u8>, Self::Error> {
+		std::fs::read(name)
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IncludeErrorKind<E> {
+	Syntax(ErrorKind),
+	/// An include group doesn't consist of `include` and one path.
+	InvalidInclude,
+	/// A file includes itself, directly or indirectly.
+	Cycle,
+	/// The resolver failed to resolve or load a file.
+	Resolve(E),
+}
+
+impl<E: fmt::Display> fmt::Display for IncludeErrorKind<E> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Syntax(k) => write!(f, "{}, expected {}", k, k.expected()),
+			Self::InvalidInclude => f.write_str("invalid include, expected `(include \"path\")`"),
+			Self::Cycle => f.write_str("include cycle"),
+			Self::Resolve(e) => e.fmt(f),
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IncludeError<E> {
+	pub kind: IncludeErrorKind<E>,
+	/// The name of the file containing the error.
+	///
+	/// For resolver errors and cycles this is the file with the offending include.
+	pub file: String,
+	/// Byte offset of the error in [`IncludeError::file`].
+	pub offset: usize,
+	pub line_col: LineCol,
+	/// The name and position of each include that led to [`IncludeError::file`],
+	/// innermost first.
+	pub included_from: Vec<(String, LineCol)>,
+}
+
+impl<E: fmt::Display> fmt::Display for IncludeError<E> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}:{}: {}", self.file, self.line_col, self.kind)?;
+		for (file, lc) in &self.included_from {
+			write!(f, ", included from {}:{}", file, lc)?;
+		}
+		Ok(())
+	}
+}
+
+impl<E: fmt::Debug + fmt::Display> core::error::Error for IncludeError<E> {}
+
+/// Load the file at `path` and expand all includes in it.
+pub fn load<R: Resolver>(
+	resolver: &mut R,
+	path: &str,
+) -> Result<Document<'static>, IncludeError<R::Error>> {
+	let fail = |e| IncludeError {
+		kind: IncludeErrorKind::Resolve(e),
+		file: path.into(),
+		offset: 0,
+		line_col: LineCol { line: 1, column: 1 },
+		included_from: Vec::new(),
+	};
+	let name = resolver.resolve(None, path).map_err(fail)?;
+	let data = resolver.load(&name).map_err(fail)?;
+	expand(resolver, name, &data)
+}
+
+/// Parse `data` and expand all includes in it.
+///
+/// `name` is the name of the file for resolving includes and in errors.
+pub fn expand<R: Resolver>(
+	resolver: &mut R,
+	name: String,
+	data: &[u8],
+) -> Result<Document<'static>, IncludeError<R::Error>> {
+	let mut ex = Expander {
+		resolver,
+		stack: Vec::new(),
+	};
+	ex.file(name, data).map(|root| Document { root })
+}
+
+struct Expander<'r, R> {
+	resolver: &'r mut R,
+	/// The names of the files being expanded, outermost first.
+	stack: Vec<String>,
+}
+
+impl<R: Resolver> Expander<'_, R> {
+	fn file(&mut self, name: String, data: &[u8]) -> Result<List<'static>, IncludeError<R::Error>> {
+		let mut doc = match Document::parse(data) {
+			Ok(doc) => doc,
+			Err(e) => {
+				return Err(IncludeError {
+					kind: IncludeErrorKind::Syntax(e.kind),
+					file: name,
+					offset: e.offset,
+					line_col: e.line_col,
+					included_from: Vec::new(),
+				});
+			}
+		};
+		doc.set_origin(&name, data);
+		self.stack.push(name);
+		let r = self.list(doc.root, data);
+		self.stack.pop();
+		r
+	}
+
+	fn error(
+		&self,
+		kind: IncludeErrorKind<R::Error>,
+		data: &[u8],
+		offset: usize,
+	) -> IncludeError<R::Error> {
+		IncludeError {
+			kind,
+			file: self.stack.last().cloned().unwrap_or_default(),
+			offset,
+			line_col: crate::line_col(data, offset),
+			included_from: Vec::new(),
+		}
+	}
+
+	fn list(
+		&mut self,
+		list: List<'_>,
+		data: &[u8],
+	) -> Result<List<'static>, IncludeError<R::Error>> {
+		let mut items = Vec::with_capacity(list.items.len());
+		for v in list.items {
+			let l = match v {
+				Value::List(l) if is_include(&l) => l,
+				Value::List(l) => {
+					items.push(Value::List(self.list(l, data)?));
+					continue;
+				}
+				Value::Atom(t) => {
+					items.push(Value::Atom(t.into_owned()));
+					continue;
+				}
+			};
+			let start = l.span.start;
+			let [_, Value::Atom(path)] = &l.items[..] else {
+				return Err(self.error(IncludeErrorKind::InvalidInclude, data, start));
+			};
+			let from = self.stack.last().map(String::as_str);
+			let name = match self.resolver.resolve(from, path.as_str()) {
+				Ok(name) if self.stack.contains(&name) => {
+					return Err(self.error(IncludeErrorKind::Cycle, data, start));
+				}
+				Ok(name) => name,
+				Err(e) => return Err(self.error(IncludeErrorKind::Resolve(e), data, start)),
+			};
+			let included = match self.resolver.load(&name) {
+				Ok(d) => d,
+				Err(e) => return Err(self.error(IncludeErrorKind::Resolve(e), data, start)),
+			};
+			match self.file(name, &included) {
+				Ok(l) => items.extend(l.items),
+				Err(mut e) => {
+					let here = self.stack.last().cloned().unwrap_or_default();
+					e.included_from.push((here, crate::line_col(data, start)));
+					return Err(e);
+				}
+			}
+		}
+		Ok(List {
+			items,
+			span: list.span,
+			origin: list.origin,
+		})
+	}
+}
+
+/// Whether `l` is headed by the bare symbol `include`.
+fn is_include(l: &List<'_>) -> bool {
+	matches!(l.items.first(), Some(Value::Atom(t)) if t.quote.is_none() && t.text == "include")
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use alloc::string::ToString;
+
+	/// Names are paths as written, ignoring the including file.
+	struct Files(&'static [(&'static str, &'static str)]);
+
+	impl Resolver for Files {
+		type Error = &'static str;
+
+		fn resolve(&mut self, _: Option<&str>, path: &str) -> Result<String, Self::Error> {
+			Ok(path.into())
+		}
+
+		fn load(&mut self, name: &str) -> Result<Vec<u8>, Self::Error> {
+			let f = self.0.iter().find(|(n, _)| *n == name);
+			f.map(|(_, d)| d.as_bytes().into()).ok_or("not found")
+		}
+	}
+
+	#[test]
+	fn include() {
+		let mut files = Files(&[
+			(
+				"pci",
+				"(pci-drivers\n\t(include \"red-hat\")\n\t(include intel))\n(include common)",
+			),
+			("red-hat", "(1af4 (1000 net) (include common))"),
+			("intel", "(8086 (1616 hd_graphics))\n(8087)"),
+			("common", "(version 1)"),
+		]);
+		let doc = load(&mut files, "pci").unwrap();
+		assert_eq!(
+			doc.to_string(),
+			"(pci-drivers (1af4 (1000 net) (version 1)) (8086 (1616 hd_graphics)) (8087))\n\
+			(version 1)\n"
+		);
+		// Spans are relative to the included file.
+		let intel = &doc["pci-drivers"]["8086"];
+		assert_eq!(intel.span.start, 0);
+		assert_eq!(intel.origin.as_ref().unwrap().to_string(), "intel:1:1");
+		let o = doc.root.items[1].origin().unwrap();
+		assert_eq!(o.to_string(), "common:1:1");
+		let o = doc["pci-drivers"].items[0].origin().unwrap();
+		assert_eq!(o.to_string(), "pci:1:2");
+
+		let doc = expand(&mut files, "x".into(), b"(\"include\" common)").unwrap();
+		assert_eq!(doc.to_string(), "(\"include\" common)\n");
+	}
+
+	#[test]
+	fn errors() {
+		let mut files = Files(&[
+			("a", "(x\n  (include b))"),
+			("b", "(y) (include c)"),
+			("c", "(z (include a))"),
+			("bad", "(y)\n(z"),
+			("invalid", "(include)"),
+		]);
+		let e = load(&mut files, "a").unwrap_err();
+		assert_eq!(e.kind, IncludeErrorKind::Cycle);
+		assert_eq!((e.file.as_str(), e.offset), ("c", 3));
+		assert_eq!(
+			e.to_string(),
+			"c:1:4: include cycle, included from b:1:5, included from a:2:3"
+		);
+
+		let e = expand(&mut files, "top".into(), b"\n(include bad)").unwrap_err();
+		assert_eq!(e.kind, IncludeErrorKind::Syntax(ErrorKind::UnclosedGroup));
+		assert_eq!((e.file.as_str(), e.offset), ("bad", 4));
+		assert_eq!(
+			e.included_from,
+			[("top".into(), LineCol { line: 2, column: 1 })]
+		);
+
+		let e = expand(&mut files, "top".into(), b"(include missing)").unwrap_err();
+		assert_eq!(e.kind, IncludeErrorKind::Resolve("not found"));
+		assert_eq!(e.file, "top");
+
+		let e = load(&mut files, "invalid").unwrap_err();
+		assert_eq!(e.kind, IncludeErrorKind::InvalidInclude);
+		let e = expand(&mut files, "top".into(), b"(include (a))").unwrap_err();
+		assert_eq!(e.kind, IncludeErrorKind::InvalidInclude);
+
+		let e = load(&mut files, "missing").unwrap_err();
+		assert_eq!((e.file.as_str(), e.offset), ("missing", 0));
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn fs() {
+		let dir = std::env::temp_dir().join(std::format!("scf-include-{}", std::process::id()));
+		std::fs::create_dir_all(dir.join("vendor")).unwrap();
+		std::fs::write(
+			dir.join("pci.scf"),
+			"(pci-drivers (include \"vendor/intel.scf\"))",
+		)
+		.unwrap();
+		std::fs::write(
+			dir.join("vendor/intel.scf"),
+			"(8086 (include \"../common.scf\"))",
+		)
+		.unwrap();
+		std::fs::write(dir.join("common.scf"), "(version 1)").unwrap();
+		let doc = load(&mut FsResolver, dir.join("pci.scf").to_str().unwrap());
+		std::fs::remove_dir_all(&dir).unwrap();
+		assert_eq!(
+			doc.unwrap().to_string(),
+			"(pci-drivers (8086 (version 1)))\n"
+		);
+	}
+}
@@ -0,0 +1,816 @@
+//! Convert documents to and from JSON.
+//!
+//! # S to JSON
+//!
+//! With [`GroupStyle::Object`], a list is converted depending on its items,
+//! which for a group with a head are the items after the head:
+//!
+//! - No items become `[]`: `(debug)` is `"debug": []`.
+//! - A single symbol or string becomes that scalar: `(console ttyS0)` is `"console": "ttyS0"`.
+//! - Only groups with a head become an object keyed by head:
+//!   `(net (mtu 9000) (dhcp yes))` is `"net": {"mtu": 9000, "dhcp": "yes"}`.
+//! - Anything else becomes an array: `(modules a b)` is `"modules": ["a", "b"]`.
+//!   A group in an array is converted as a whole, so it becomes an object
+//!   if all its items are groups with a head and an array otherwise: `(x 1)` is `["x", 1]`.
+//!
+//! The document itself is converted like the items of a group,
+//! so a typical configuration becomes an object.
+//! If a head occurs more than once among the items, [`Repeated`] decides what happens.
+//!
+//! With [`GroupStyle::Array`], every list becomes an array of its items, including the head.
+//! This is more regular but harder to query.
+//!
+//! If [`JsonOptions::infer`] is set, bare symbols that are valid JSON numbers
+//! or are `true`, `false` or `null` become those values. Other symbols and strings
+//! become JSON strings.
+//!
+//! # JSON to S
+//!
+//! This reverses both group styles:
+//!
+//! - An object becomes groups with the keys as heads, followed by the items for the value.
+//! - As the value of a key or the whole document, an array gives one item per element
+//!   and a scalar gives a single item.
+//!   Everywhere else an array or object becomes a group.
+//! - Strings become bare symbols if possible.
+//!   With [`JsonOptions::infer`], strings that would be read back as another type are quoted,
+//!   except for keys, which are always read back as strings.
+//! - Numbers, `true`, `false` and `null` become bare symbols.
+//!
+//! Converting a document to JSON and back therefore gives an equal document,
+//! except for quotes, unless [`Repeated::Collect`] or [`Repeated::Last`] changed it.
+//!
+//! ```
+//! use scf::{json, Document};
+//!
+//! let doc = Document::parse(b"(console ttyS0) (modules a b) (net (mtu 9000) (dhcp yes))").unwrap();
+//! let options = json::JsonOptions::default();
+//! let s = json::to_json_string(&doc, &options).unwrap();
+//! assert_eq!(
+//!     s,
+//!     r#"{"console":"ttyS0","modules":["a","b"],"net":{"mtu":9000,"dhcp":"yes"}}"#,
+//! );
+//! assert_eq!(json::from_json(s.as_bytes(), &options).unwrap(), doc);
+//! ```
+
+use crate::{Document, LineCol, List, Span, Text, Value};
+use alloc::{
+	string::{String, ToString},
+	vec::Vec,
+};
+use core::fmt;
+
+/// How lists are converted to JSON.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum GroupStyle {
+	/// Groups with a head become keys in an object where possible.
+	#[default]
+	Object,
+	/// Every list becomes an array.
+	Array,
+}
+
+/// What to do with a head that occurs more than once in a list that would become an object.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Repeated {
+	/// Convert the list to an array instead.
+	#[default]
+	Array,
+	/// Put the values of all groups with the head in an array, at the position of the first.
+	///
+	/// `(a 1) (a 2)` becomes `{"a": [1, 2]}`, which converts back to `(a 1 2)`.
+	Collect,
+	/// Keep only the last group with the head.
+	///
+	/// This matches the [`Merger`](crate::merge::Merger), where later layers override earlier ones.
+	Last,
+	/// Fail with [`ToJsonError::Repeated`].
+	Error,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct JsonOptions {
+	pub groups: GroupStyle,
+	pub repeated: Repeated,
+	/// Convert bare symbols that look like numbers, `true`, `false` or `null` to those values.
+	pub infer: bool,
+	/// Put every element and key on its own line, indented with tabs.
+	pub pretty: bool,
+}
+
+impl Default for JsonOptions {
+	fn default() -> Self {
+		Self {
+			groups: GroupStyle::default(),
+			repeated: Repeated::default(),
+			infer: true,
+			pretty: false,
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ToJsonError {
+	/// A head occurs more than once with [`Repeated::Error`].
+	Repeated {
+		/// The heads of the groups around the repeated group and its own head, separated by `/`.
+		path: String,
+		/// The span of the second group with the head.
+		span: Span,
+	},
+	/// The underlying writer failed.
+	Fmt,
+}
+
+impl From<fmt::Error> for ToJsonError {
+	fn from(_: fmt::Error) -> Self {
+		Self::Fmt
+	}
+}
+
+impl fmt::Display for ToJsonError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Repeated { path, .. } => write!(f, "`{}` occurs more than once", path),
+			Self::Fmt => f.write_str("failed to write"),
+		}
+	}
+}
+
+impl core::error::Error for ToJsonError {}
+
+/// An error in JSON input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FromJsonError {
+	pub offset: usize,
+	pub line_col: LineCol,
+	/// What was expected at `offset`, such as "a value".
+	pub expected: &'static str,
+}
+
+impl fmt::Display for FromJsonError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"{}: invalid JSON, expected {}",
+			self.line_col, self.expected
+		)
+	}
+}
+
+impl core::error::Error for FromJsonError {}
+
+/// Write `list` as JSON.
+///
+/// Pass a [`Document`] to convert all its items.
+pub fn to_json<W: fmt::Write>(
+	list: &List<'_>,
+	out: &mut W,
+	options: &JsonOptions,
+) -> Result<(), ToJsonError> {
+	let mut w = JsonWriter {
+		out,
+		options,
+		depth: 0,
+		path: Vec::new(),
+	};
+	match options.groups {
+		GroupStyle::Object => w.tail(&list.items)?,
+		GroupStyle::Array => w.array(&list.items)?,
+	}
+	if options.pretty {
+		w.out.write_char('\n')?;
+	}
+	Ok(())
+}
+
+/// Convert `list` to a JSON string.
+pub fn to_json_string(list: &List<'_>, options: &JsonOptions) -> Result<String, ToJsonError> {
+	let mut s = String::new();
+	to_json(list, &mut s, options)?;
+	Ok(s)
+}
+
+/// Convert JSON to a document.
+///
+/// Only [`JsonOptions::infer`] affects the result.
+/// Arrays and objects can be nested at most [`MAX_DEPTH`] levels deep.
+pub fn from_json(data: &[u8], options: &JsonOptions) -> Result<Document<'static>, FromJsonError> {
+	let mut p = Parser {
+		data,
+		pos: 0,
+		depth: 0,
+		infer: options.infer,
+	};
+	let mut items = Vec::new();
+	p.items(&mut items)?;
+	p.ws();
+	if p.pos < data.len() {
+		return Err(p.error("the end of the input"));
+	}
+	Ok(Document {
+		root: List::new(items),
+	})
+}
+
+struct JsonWriter<'d, 'o, W> {
+	out: &'o mut W,
+	options: &'o JsonOptions,
+	depth: usize,
+	/// The heads of the groups around the current one.
+	path: Vec<&'d str>,
+}
+
+impl<'d, W: fmt::Write> JsonWriter<'d, '_, W> {
+	/// Write the items of a group after its head.
+	fn tail(&mut self, items: &'d [Value<'_>]) -> Result<(), ToJsonError> {
+		match items {
+			[Value::Atom(t)] => self.scalar(t),
+			_ if self.is_object(items)? => self.object(items),
+			_ => self.array(items),
+		}
+	}
+
+	/// Write an item of an array.
+	fn element(&mut self, v: &'d Value<'_>) -> Result<(), ToJsonError> {
+		match v {
+			Value::Atom(t) => self.scalar(t),
+			Value::List(l)
+				if self.options.groups == GroupStyle::Object && self.is_object(&l.items)? =>
+			{
+				self.object(&l.items)
+			}
+			Value::List(l) => self.array(&l.items),
+		}
+	}
+
+	/// Whether `items` should be written as an object.
+	fn is_object(&self, items: &'d [Value<'_>]) -> Result<bool, ToJsonError> {
+		if items.is_empty() || !items.iter().all(|v| v.head().is_some()) {
+			return Ok(false);
+		}
+		let repeated = items
+			.iter()
+			.enumerate()
+			.find(|(i, v)| items[..*i].iter().any(|w| w.head() == v.head()));
+		match (repeated, self.options.repeated) {
+			(None, _) | (_, Repeated::Collect | Repeated::Last) => Ok(true),
+			(Some(_), Repeated::Array) => Ok(false),
+			(Some((_, v)), Repeated::Error) => {
+				let mut path = self.path.clone();
+				path.extend(v.head());
+				Err(ToJsonError::Repeated {
+					path: path.join("/"),
+					span: v.span(),
+				})
+			}
+		}
+	}
+
+	fn object(&mut self, items: &'d [Value<'_>]) -> Result<(), ToJsonError> {
+		self.out.write_char('{')?;
+		self.depth += 1;
+		let mut first = true;
+		for (i, v) in items.iter().enumerate() {
+			let (Some(h), Some(l)) = (v.head(), v.as_list()) else {
+				continue;
+			};
+			let mut same = items.iter().filter(|w| w.head() == Some(h));
+			let skip = match self.options.repeated {
+				Repeated::Last => items[i + 1..].iter().any(|w| w.head() == Some(h)),
+				_ => items[..i].iter().any(|w| w.head() == Some(h)),
+			};
+			if skip {
+				continue;
+			}
+			self.separate(&mut first)?;
+			write_string(self.out, h)?;
+			self.out
+				.write_str(if self.options.pretty { ": " } else { ":" })?;
+			self.path.push(h);
+			if self.options.repeated == Repeated::Collect && same.clone().nth(1).is_some() {
+				self.out.write_char('[')?;
+				self.depth += 1;
+				let mut first = true;
+				while let Some(l) = same.next().and_then(Value::as_list) {
+					self.separate(&mut first)?;
+					self.tail(l.tail())?;
+				}
+				self.close(first, ']')?;
+			} else {
+				self.tail(l.tail())?;
+			}
+			self.path.pop();
+		}
+		self.close(first, '}')
+	}
+
+	fn array(&mut self, items: &'d [Value<'_>]) -> Result<(), ToJsonError> {
+		self.out.write_char('[')?;
+		self.depth += 1;
+		let mut first = true;
+		for v in items {
+			self.separate(&mut first)?;
+			self.element(v)?;
+		}
+		self.close(first, ']')
+	}
+
+	fn scalar(&mut self, t: &Text<'_>) -> Result<(), ToJsonError> {
+		if self.options.infer && t.quote.is_none() && is_scalar(&t.text) {
+			self.out.write_str(&t.text)?;
+		} else {
+			write_string(self.out, &t.text)?;
+		}
+		Ok(())
+	}
+
+	/// Write the separator before an element of an array or object.
+	fn separate(&mut self, first: &mut bool) -> fmt::Result {
+		if !core::mem::take(first) {
+			self.out.write_char(',')?;
+		}
+		self.newline()
+	}
+
+	/// End an array or object, where `empty` is whether nothing was written in it.
+	fn close(&mut self, empty: bool, c: char) -> Result<(), ToJsonError> {
+		self.depth -= 1;
+		if !empty {
+			self.newline()?;
+		}
+		Ok(self.out.write_char(c)?)
+	}
+
+	fn newline(&mut self) -> fmt::Result {
+		if self.options.pretty {
+			self.out.write_char('\n')?;
+			for _ in 0..self.depth {
+				self.out.write_char('\t')?;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Whether `s` is a JSON number, `true`, `false` or `null`.
+fn is_scalar(s: &str) -> bool {
+	if matches!(s, "true" | "false" | "null") {
+		return true;
+	}
+	let s = s.as_bytes();
+	let digits = |i: usize| s[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+	let mut i = usize::from(s.first() == Some(&b'-'));
+	match digits(i) {
+		0 => return false,
+		n if n > 1 && s[i] == b'0' => return false,
+		n => i += n,
+	}
+	if s.get(i) == Some(&b'.') {
+		match digits(i + 1) {
+			0 => return false,
+			n => i += 1 + n,
+		}
+	}
+	if matches!(s.get(i), Some(b'e' | b'E')) {
+		i += 1;
+		if matches!(s.get(i), Some(b'+' | b'-')) {
+			i += 1;
+		}
+		match digits(i) {
+			0 => return false,
+			n => i += n,
+		}
+	}
+	i == s.len()
+}
+
+fn write_string<W: fmt::Write>(out: &mut W, s: &str) -> fmt::Result {
+	out.write_char('"')?;
+	let mut rest = s;
+	while let Some(i) = rest.find(|c: char| c == '"' || c == '\\' || c.is_ascii_control()) {
+		out.write_str(&rest[..i])?;
+		match rest.as_bytes()[i] {
+			b'"' => out.write_str("\\\"")?,
+			b'\\' => out.write_str("\\\\")?,
+			b'\n' => out.write_str("\\n")?,
+			b'\t' => out.write_str("\\t")?,
+			b'\r' => out.write_str("\\r")?,
+			c => write!(out, "\\u{:04x}", c)?,
+		}
+		rest = &rest[i + 1..];
+	}
+	out.write_str(rest)?;
+	out.write_char('"')
+}
+
+/// The maximum nesting of arrays and objects accepted by [`from_json`].
+pub const MAX_DEPTH: usize = 128;
+
+struct Parser<'d> {
+	data: &'d [u8],
+	pos: usize,
+	/// The amount of arrays and objects the parser is in.
+	depth: usize,
+	infer: bool,
+}
+
+impl Parser<'_> {
+	fn error(&self, expected: &'static str) -> FromJsonError {
+		FromJsonError {
+			offset: self.pos,
+			line_col: crate::line_col(self.data, self.pos),
+			expected,
+		}
+	}
+
+	fn ws(&mut self) {
+		while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+			self.pos += 1;
+		}
+	}
+
+	fn peek(&self) -> Option<u8> {
+		self.data.get(self.pos).copied()
+	}
+
+	/// Skip `c` if it is next.
+	fn eat(&mut self, c: u8) -> bool {
+		self.ws();
+		let eq = self.peek() == Some(c);
+		self.pos += usize::from(eq);
+		eq
+	}
+
+	/// Parse a value as the items of a group.
+	fn items(&mut self, out: &mut Vec<Value<'static>>) -> Result<(), FromJsonError> {
+		if self.eat(b'[') {
+			self.nested(|p| {
+				p.seq(b']', "`,` or `]`", |p| {
+					let v = p.element()?;
+					out.push(v);
+					Ok(())
+				})
+			})
+		} else if self.eat(b'{') {
+			self.nested(|p| p.object(out))
+		} else {
+			let v = self.scalar()?;
+			out.push(v);
+			Ok(())
+		}
+	}
+
+	/// Parse a value as a single item.
+	fn element(&mut self) -> Result<Value<'static>, FromJsonError> {
+		let mut items = Vec::new();
+		if self.eat(b'[') {
+			self.nested(|p| {
+				p.seq(b']', "`,` or `]`", |p| {
+					items.push(p.element()?);
+					Ok(())
+				})
+			})?;
+		} else if self.eat(b'{') {
+			self.nested(|p| p.object(&mut items))?;
+		} else {
+			return self.scalar();
+		}
+		Ok(List::new(items).into())
+	}
+
+	/// Run `f` one level deeper, failing if that exceeds [`MAX_DEPTH`].
+	fn nested(
+		&mut self,
+		f: impl FnOnce(&mut Self) -> Result<(), FromJsonError>,
+	) -> Result<(), FromJsonError> {
+		if self.depth == MAX_DEPTH {
+			return Err(self.error("at most 128 nested arrays and objects"));
+		}
+		self.depth += 1;
+		f(self)?;
+		self.depth -= 1;
+		Ok(())
+	}
+
+	/// Parse the members of an object after `{` as groups.
+	fn object(&mut self, out: &mut Vec<Value<'static>>) -> Result<(), FromJsonError> {
+		self.seq(b'}', "`,` or `}`", |p| {
+			p.ws();
+			if p.peek() != Some(b'"') {
+				return Err(p.error("a string"));
+			}
+			// Keys are always strings, so they don't need quotes to keep their type.
+			let key = p.string()?;
+			let key = if crate::is_symbol(&key) {
+				Text::new(key)
+			} else {
+				Text::quoted(key)
+			};
+			let mut items = Vec::from([Value::Atom(key)]);
+			if !p.eat(b':') {
+				return Err(p.error("`:`"));
+			}
+			p.items(&mut items)?;
+			out.push(List::new(items).into());
+			Ok(())
+		})
+	}
+
+	/// Parse elements separated by commas until `end`.
+	fn seq(
+		&mut self,
+		end: u8,
+		expected: &'static str,
+		mut f: impl FnMut(&mut Self) -> Result<(), FromJsonError>,
+	) -> Result<(), FromJsonError> {
+		if self.eat(end) {
+			return Ok(());
+		}
+		loop {
+			f(self)?;
+			if self.eat(end) {
+				return Ok(());
+			}
+			if !self.eat(b',') {
+				return Err(self.error(expected));
+			}
+		}
+	}
+
+	fn scalar(&mut self) -> Result<Value<'static>, FromJsonError> {
+		self.ws();
+		let start = self.pos;
+		if self.peek() == Some(b'"') {
+			let s = self.string()?;
+			let bare = crate::is_symbol(&s) && !(self.infer && is_scalar(&s));
+			return Ok(Value::Atom(if bare {
+				Text::new(s)
+			} else {
+				Text::quoted(s)
+			}));
+		}
+		let len = self.data[start..]
+			.iter()
+			.take_while(|c| c.is_ascii_alphanumeric() || matches!(c, b'-' | b'+' | b'.'))
+			.count();
+		let s = core::str::from_utf8(&self.data[start..start + len]).unwrap_or_default();
+		if len == 0 || !is_scalar(s) {
+			return Err(self.error("a value"));
+		}
+		self.pos += len;
+		Ok(Value::Atom(Text::new(s.to_string())))
+	}
+
+	/// Parse a string, starting at the opening quote.
+	fn string(&mut self) -> Result<String, FromJsonError> {
+		let start = self.pos;
+		self.pos += 1;
+		let mut s = Vec::new();
+		loop {
+			let Some(c) = self.peek() else {
+				return Err(self.error("a closing `\"`"));
+			};
+			match c {
+				b'"' => break,
+				b'\\' => {
+					self.pos += 1;
+					let c = match self.peek() {
+						Some(c @ (b'"' | b'\\' | b'/')) => char::from(c),
+						Some(b'b') => '\x08',
+						Some(b'f') => '\x0c',
+						Some(b'n') => '\n',
+						Some(b'r') => '\r',
+						Some(b't') => '\t',
+						Some(b'u') => self.unicode()?,
+						_ => return Err(self.error("a valid escape sequence")),
+					};
+					s.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+				}
+				..b' ' => return Err(self.error("an escape sequence for control characters")),
+				c => s.push(c),
+			}
+			self.pos += 1;
+		}
+		self.pos += 1;
+		String::from_utf8(s).map_err(|_| FromJsonError {
+			offset: start,
+			line_col: crate::line_col(self.data, start),
+			expected: "a string of valid UTF-8",
+		})
+	}
+
+	/// Parse the digits of a `\u` escape and a following low surrogate, leaving `pos` at the last digit.
+	fn unicode(&mut self) -> Result<char, FromJsonError> {
+		let hex = |p: &mut Self| {
+			let digits = p.data.get(p.pos + 1..p.pos + 5);
+			let n = digits
+				.and_then(|d| core::str::from_utf8(d).ok())
+				.filter(|d| d.bytes().all(|c| c.is_ascii_hexdigit()))
+				.and_then(|d| u32::from_str_radix(d, 16).ok())
+				.ok_or_else(|| p.error("4 hexadecimal digits"))?;
+			p.pos += 4;
+			Ok(n)
+		};
+		let n = hex(self)?;
+		let n = if (0xd800..0xdc00).contains(&n) {
+			if self.data.get(self.pos + 1..self.pos + 3) != Some(b"\\u") {
+				return Err(self.error("a low surrogate"));
+			}
+			self.pos += 2;
+			match hex(self)? {
+				m @ 0xdc00..0xe000 => 0x10000 + ((n - 0xd800) << 10) + (m - 0xdc00),
+				_ => return Err(self.error("a low surrogate")),
+			}
+		} else {
+			n
+		};
+		char::from_u32(n).ok_or_else(|| self.error("a valid code point"))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::fixture::{doc, PCI};
+
+	const NET: &[u8] = br#"(hostname "build-01")
+(net
+	(eth0 (mtu 9000) (dhcp true) (address "10.0.0.2"))
+	(routes (0.0.0.0/0 eth0) (10.1.0.0/16 eth1)))
+(modules a b)
+(debug)
+(users (user alice wheel) (user bob))"#;
+
+	fn json(doc: &Document<'_>, options: JsonOptions) -> String {
+		to_json_string(doc, &options).unwrap()
+	}
+
+	/// Check that converting `s` back to S gives the same JSON.
+	fn round_trip(s: &str, options: JsonOptions) {
+		let doc = from_json(s.as_bytes(), &options).unwrap();
+		assert_eq!(json(&doc, options), s);
+	}
+
+	#[test]
+	fn object() {
+		let doc = Document::parse(NET).unwrap();
+		let options = JsonOptions::default();
+		let s = json(&doc, options);
+		assert_eq!(
+			s,
+			r#"{"hostname":"build-01","net":{"eth0":{"mtu":9000,"dhcp":true,"address":"10.0.0.2"},"routes":{"0.0.0.0/0":"eth0","10.1.0.0/16":"eth1"}},"modules":["a","b"],"debug":[],"users":[["user","alice","wheel"],["user","bob"]]}"#
+		);
+		round_trip(&s, options);
+
+		let pretty = JsonOptions {
+			pretty: true,
+			..options
+		};
+		let s = json(&doc, pretty);
+		assert!(s.starts_with("{\n\t\"hostname\": \"build-01\",\n\t\"net\": {\n\t\t\"eth0\": {\n"));
+		assert!(s.contains("\t\"debug\": [],\n\t\"users\": [\n\t\t[\n\t\t\t\"user\",\n"));
+		round_trip(&s, pretty);
+
+		let doc = Document::parse(br#"x (a 1) ((b)) () "c d""#).unwrap();
+		let s = json(&doc, options);
+		assert_eq!(s, r#"["x",["a",1],{"b":[]},[],"c d"]"#);
+		assert_eq!(from_json(s.as_bytes(), &options).unwrap(), doc);
+	}
+
+	#[test]
+	fn keys() {
+		let d = Document::parse(PCI).unwrap();
+		let options = JsonOptions::default();
+		let s = json(&d, options);
+		assert_eq!(
+			s,
+			r#"{"pci-drivers":{"1af4":{"1000":"drivers/pci/virtio/net","1001":"drivers/pci/virtio/blk","1040":"drivers/pci/virtio/gpu"},"8086":{"1616":"drivers/pci/intel/hd_graphics"}}}"#
+		);
+		let back = from_json(s.as_bytes(), &options).unwrap();
+		// The strings are symbols, so only their quotes are lost.
+		let unquoted = core::str::from_utf8(PCI).unwrap().replace('"', "");
+		assert_eq!(back, doc(&unquoted));
+		assert_eq!(
+			back.lookup("pci-drivers/8086/1616").unwrap().tail(),
+			[Value::Atom(Text::new("drivers/pci/intel/hd_graphics"))]
+		);
+
+		let back = from_json(br#"{"a b": {"1": 2, "null": null}}"#, &options).unwrap();
+		assert_eq!(back.to_string(), "(\"a b\" (1 2) (null null))\n");
+	}
+
+	#[test]
+	fn array() {
+		let doc = Document::parse(NET).unwrap();
+		let options = JsonOptions {
+			groups: GroupStyle::Array,
+			..Default::default()
+		};
+		let s = json(&doc, options);
+		assert!(s.starts_with(r#"[["hostname","build-01"],["net",["eth0",["mtu",9000],"#));
+		round_trip(&s, options);
+	}
+
+	#[test]
+	fn repeated() {
+		let doc = Document::parse(b"(a 1) (b (c x) (c y)) (a 2 3)").unwrap();
+		let s = |repeated| {
+			to_json_string(
+				&doc,
+				&JsonOptions {
+					repeated,
+					..Default::default()
+				},
+			)
+		};
+		assert_eq!(
+			s(Repeated::Array).unwrap(),
+			r#"[["a",1],["b",["c","x"],["c","y"]],["a",2,3]]"#
+		);
+		assert_eq!(
+			s(Repeated::Collect).unwrap(),
+			r#"{"a":[1,[2,3]],"b":{"c":["x","y"]}}"#
+		);
+		assert_eq!(s(Repeated::Last).unwrap(), r#"{"b":{"c":"y"},"a":[2,3]}"#);
+		let doc = Document::parse(b"(b (c x) (c y))").unwrap();
+		let e = to_json_string(
+			&doc,
+			&JsonOptions {
+				repeated: Repeated::Error,
+				..Default::default()
+			},
+		);
+		assert_eq!(e.unwrap_err().to_string(), "`b/c` occurs more than once");
+		assert_eq!(
+			s(Repeated::Error).unwrap_err(),
+			ToJsonError::Repeated {
+				path: "a".to_string(),
+				span: Span { start: 22, end: 29 }
+			}
+		);
+	}
+
+	#[test]
+	fn scalars() {
+		let doc = Document::parse(br#"(n 1 -0.5 2e10 01 1a "2" true "null" x)"#).unwrap();
+		let s = json(&doc, JsonOptions::default());
+		assert_eq!(s, r#"{"n":[1,-0.5,2e10,"01","1a","2",true,"null","x"]}"#);
+		let back = from_json(s.as_bytes(), &JsonOptions::default()).unwrap();
+		assert_eq!(back, doc);
+		assert_eq!(
+			back.to_string(),
+			"(n 1 -0.5 2e10 01 1a \"2\" true \"null\" x)\n"
+		);
+
+		let options = JsonOptions {
+			infer: false,
+			..Default::default()
+		};
+		let s = json(&doc, options);
+		assert_eq!(
+			s,
+			r#"{"n":["1","-0.5","2e10","01","1a","2","true","null","x"]}"#
+		);
+		let back = from_json(s.as_bytes(), &options).unwrap();
+		assert_eq!(back.to_string(), "(n 1 -0.5 2e10 01 1a 2 true null x)\n");
+
+		let doc = from_json(br#"{"a b": "\"\u00e9\ud83d\ude00\n", "": null}"#, &options).unwrap();
+		assert_eq!(doc.to_string(), "(\"a b\" \"\\\"é😀\\n\")\n(\"\" null)\n");
+		assert_eq!(json(&doc, options), r#"{"a b":"\"é😀\n","":"null"}"#);
+	}
+
+	#[test]
+	fn errors() {
+		let e = |s: &str| {
+			let e = from_json(s.as_bytes(), &JsonOptions::default()).unwrap_err();
+			(e.to_string(), e.offset)
+		};
+		assert_eq!(
+			e(""),
+			("1:1: invalid JSON, expected a value".to_string(), 0)
+		);
+		assert_eq!(e("[1 2]").1, 3);
+		assert_eq!(e("{\"a\" 1}").0, "1:6: invalid JSON, expected `:`");
+		assert_eq!(e("{\n\t1: 2}").0, "2:2: invalid JSON, expected a string");
+		assert_eq!(e("\"\\q\"").1, 2);
+		assert_eq!(
+			e("\"\\ud800\"").0,
+			"1:7: invalid JSON, expected a low surrogate"
+		);
+		assert_eq!(e("[01]").1, 1);
+		assert_eq!(
+			e("[] []").0,
+			"1:4: invalid JSON, expected the end of the input"
+		);
+		assert_eq!(e("\"a").1, 2);
+
+		let deep = |n| "[".repeat(n) + &"]".repeat(n);
+		assert!(from_json(deep(MAX_DEPTH).as_bytes(), &JsonOptions::default()).is_ok());
+		assert_eq!(
+			e(&deep(MAX_DEPTH + 1)).0,
+			"1:130: invalid JSON, expected at most 128 nested arrays and objects"
+		);
+		assert_eq!(e(&"{\"a\":".repeat(100_000)).1, MAX_DEPTH * 5 + 1);
+	}
+}
@@ -1,105 +1,405 @@
 #![cfg_attr(not(test), no_std)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(all(feature = "std", not(test)))]
+extern crate std;
+
+#[cfg(feature = "alloc")]
+pub mod cst;
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod decode;
+#[cfg(feature = "alloc")]
+pub mod diff;
+mod escape;
+#[cfg(all(test, feature = "alloc"))]
+mod fixture;
+mod format;
+#[cfg(feature = "alloc")]
+pub mod include;
+#[cfg(feature = "alloc")]
+pub mod json;
+#[cfg(feature = "alloc")]
+pub mod merge;
+#[cfg(feature = "alloc")]
+pub mod patch;
+mod path;
+#[cfg(feature = "serde")]
+pub mod ser;
+pub mod stream;
+#[cfg(feature = "alloc")]
+pub mod three_way;
+#[cfg(feature = "alloc")]
+mod value;
+mod writer;
+
+#[cfg(feature = "serde")]
+pub use de::{from_slice, from_str};
+pub use decode::{decode, DecodeError, DecodeErrorKind, FromScf, FromStrRadix};
+pub use escape::*;
+pub use format::*;
+pub use path::*;
+#[cfg(feature = "derive")]
+pub use scf_derive::FromScf;
+#[cfg(feature = "serde")]
+pub use ser::{to_string, to_string_pretty, to_writer, to_writer_pretty};
+#[cfg(feature = "alloc")]
+pub use value::*;
+pub use writer::*;
+
 use core::{cell::Cell, str};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Token<'a> {
 	Begin,
 	End,
+	/// A bare symbol.
 	Str(&'a str),
+	/// A quoted string. Escape sequences are not decoded.
+	Quoted(&'a str, Quote),
 }
 
 impl<'a> Token<'a> {
+	/// Get the text of either a bare symbol or a quoted string.
 	pub fn into_str(self) -> Option<&'a str> {
 		match self {
-			Self::Str(s) => Some(s),
+			Self::Str(s) | Self::Quoted(s, _) => Some(s),
 			_ => None,
 		}
 	}
+
+	/// The kind of quotes around a string, if any.
+	pub fn quote(&self) -> Option<Quote> {
+		match self {
+			Self::Quoted(_, q) => Some(*q),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Quote {
+	/// `"`
+	Double,
+	/// `'`
+	Single,
+}
+
+impl Quote {
+	pub fn as_char(&self) -> char {
+		match self {
+			Self::Double => '"',
+			Self::Single => '\'',
+		}
+	}
+}
+
+/// A range of bytes in the data passed to [`parse`] or [`parse2`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Span {
+	pub start: usize,
+	pub end: usize,
+}
+
+impl Span {
+	pub fn len(&self) -> usize {
+		self.end - self.start
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.start == self.end
+	}
+
+	/// The line and column of the start of this span.
+	pub fn line_col(&self, data: &[u8]) -> LineCol {
+		line_col(data, self.start)
+	}
 }
 
+/// A 1-based line and column. Columns are counted in characters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineCol {
+	pub line: usize,
+	pub column: usize,
+}
+
+impl core::fmt::Display for LineCol {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		write!(f, "{}:{}", self.line, self.column)
+	}
+}
+
+/// Convert a byte offset in `data` to a line and column.
+///
+/// Offsets past the end of `data` are clamped.
+pub fn line_col(data: &[u8], offset: usize) -> LineCol {
+	let data = &data[..offset.min(data.len())];
+	let line_start = data.iter().rposition(|&c| c == b'\n').map_or(0, |i| i + 1);
+	LineCol {
+		line: data.iter().filter(|&&c| c == b'\n').count() + 1,
+		// Don't count UTF-8 continuation bytes.
+		column: data[line_start..]
+			.iter()
+			.filter(|&&c| c & 0xc0 != 0x80)
+			.count() + 1,
+	}
+}
+
+/// The position after `data`, given the position at the start of it.
+pub(crate) fn advance(mut lc: LineCol, data: &[u8]) -> LineCol {
+	for &c in data {
+		if c == b'\n' {
+			lc = LineCol {
+				line: lc.line + 1,
+				column: 1,
+			};
+		} else if c & 0xc0 != 0x80 {
+			lc.column += 1;
+		}
+	}
+	lc
+}
+
+/// Whether `c` may appear in a bare symbol.
+///
+/// Symbols may not contain whitespace, parentheses, quotes, `;`, `\`
+/// or ASCII control characters. Any other byte is allowed,
+/// though the symbol as a whole must still be valid UTF-8.
+pub fn is_symbol_byte(c: u8) -> bool {
+	!(c.is_ascii_whitespace()
+		|| c.is_ascii_control()
+		|| matches!(c, b'(' | b')' | b'"' | b'\'' | b';' | b'\\'))
+}
+
+/// Whether `s` can be written as a bare symbol without quotes.
+pub fn is_symbol(s: &str) -> bool {
+	!s.is_empty() && s.bytes().all(is_symbol_byte)
+}
+
+#[derive(Clone, Debug)]
 pub struct Iter<'a> {
 	data: &'a [u8],
 	index: usize,
+	permissive: bool,
 }
 
-impl<'a> Iterator for Iter<'a> {
-	type Item = Result<Token<'a>, Error>;
+impl<'a> Iter<'a> {
+	pub(crate) fn new(data: &'a [u8]) -> Self {
+		Self {
+			data,
+			index: 0,
+			permissive: false,
+		}
+	}
 
-	fn next(&mut self) -> Option<Self::Item> {
-		let ret_str = |s| {
-			str::from_utf8(s)
-				.map_err(|_| Error::InvalidUtf8)
-				.map(|s| Token::Str(s))
+	/// Accept any byte in bare symbols except whitespace and parentheses,
+	/// instead of only those allowed by [`is_symbol_byte`].
+	pub fn permissive(mut self) -> Self {
+		self.permissive = true;
+		self
+	}
+
+	/// Like [`Iterator::next`] but also returns the span of the token.
+	///
+	/// The span of a quoted string includes the quotes.
+	pub fn next_spanned(&mut self) -> Option<Result<(Token<'a>, Span), Error>> {
+		let data = self.data;
+		let ret_str = |start: usize, end: usize, span, quote| {
+			str::from_utf8(&data[start..end])
+				.map_err(|e| Error::new(data, ErrorKind::InvalidUtf8, start + e.valid_up_to()))
+				.map(|s| match quote {
+					None => (Token::Str(s), span),
+					Some(q) => (Token::Quoted(s, q), span),
+				})
 		};
 		loop {
 			let c = self.data.get(self.index)?;
+			let span = Span {
+				start: self.index,
+				end: self.index + 1,
+			};
 			self.index += 1;
 			match c {
 				c if c.is_ascii_whitespace() => {}
-				b'(' => return Some(Ok(Token::Begin)),
-				b')' => return Some(Ok(Token::End)),
-				lim @ b'"' | lim @ b'\'' => loop {
+				b'(' => return Some(Ok((Token::Begin, span))),
+				b')' => return Some(Ok((Token::End, span))),
+				lim @ b'"' | lim @ b'\'' => {
 					let start = self.index;
 					while let Some(&c) = self.data.get(self.index) {
 						self.index += 1;
 						match c {
-							b'\\' => self.index += 1,
+							b'\\' if self.index < self.data.len() => {
+								let Some((_, len)) = escape::decode(&self.data[self.index..])
+								else {
+									let kind = ErrorKind::InvalidEscape;
+									return Some(Err(Error::new(data, kind, self.index - 1)));
+								};
+								self.index += len;
+							}
 							c if c == *lim => {
-								return Some(ret_str(&self.data[start..self.index - 1]));
+								let span = Span {
+									start: start - 1,
+									end: self.index,
+								};
+								let q = if c == b'"' {
+									Quote::Double
+								} else {
+									Quote::Single
+								};
+								return Some(ret_str(start, self.index - 1, span, Some(q)));
 							}
 							_ => {}
 						}
 					}
-					return Some(Err(Error::UnterminatedQuote));
-				},
+					let kind = ErrorKind::UnterminatedQuote;
+					return Some(Err(Error::new(data, kind, start - 1)));
+				}
 				b';' => {
-					while self.data.get(self.index).map_or(false, |c| *c != b'\n') {
+					while self.data.get(self.index).is_some_and(|c| *c != b'\n') {
 						self.index += 1;
 					}
 				}
-				_ => loop {
+				_ => {
 					let start = self.index - 1;
+					self.index -= 1;
 					while let Some(&c) = self.data.get(self.index) {
-						self.index += 1;
 						match c {
-							c if c == b'(' || c == b')' || c.is_ascii_whitespace() => {
-								self.index -= 1;
-								break;
+							c if c == b'(' || c == b')' || c.is_ascii_whitespace() => break,
+							c if !self.permissive && !is_symbol_byte(c) => {
+								let kind = ErrorKind::InvalidSymbolChar;
+								return Some(Err(Error::new(data, kind, self.index)));
 							}
-							_ => {}
+							_ => self.index += 1,
 						}
 					}
-					return Some(ret_str(&self.data[start..self.index]));
-				},
+					let span = Span {
+						start,
+						end: self.index,
+					};
+					return Some(ret_str(start, self.index, span, None));
+				}
 			}
 		}
 	}
 }
 
+impl<'a> Iterator for Iter<'a> {
+	type Item = Result<Token<'a>, Error>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.next_spanned().map(|r| r.map(|(tk, _)| tk))
+	}
+}
+
 #[derive(Debug)]
 #[must_use = "an error may have occured"]
 pub struct Groups<'a> {
 	data: &'a [u8],
 	index: Cell<usize>,
+	error: Cell<Option<Error>>,
+	permissive: bool,
 }
 
 impl<'a> Groups<'a> {
+	/// Accept any byte in bare symbols except whitespace and parentheses,
+	/// instead of only those allowed by [`is_symbol_byte`].
+	pub fn permissive(mut self) -> Self {
+		self.permissive = true;
+		self
+	}
+
 	pub fn iter(&mut self) -> GroupsIter<'a, '_> {
-		GroupsIter { inner: Some(self) }
+		let span = Span {
+			start: 0,
+			end: self.data.len(),
+		};
+		GroupsIter {
+			groups: self,
+			done: false,
+			nested: false,
+			span,
+			start: None,
+		}
+	}
+
+	/// The error that stopped iteration, if any.
+	pub fn error(&self) -> Option<Error> {
+		self.error.get()
 	}
 
 	pub fn into_error(self) -> Option<Error> {
-		Error::from_num(self.index.get())
+		self.error()
 	}
 }
 
 #[derive(Debug)]
 pub struct GroupsIter<'a, 'b> {
-	inner: Option<&'b Groups<'a>>,
+	groups: &'b Groups<'a>,
+	done: bool,
+	/// Whether this group was opened with a `(`, i.e. it is not the top level.
+	nested: bool,
+	span: Span,
+	/// The offset of the first item returned by `next`, where `rewind` restarts.
+	start: Option<usize>,
 }
 
 impl<'a, 'b> GroupsIter<'a, 'b> {
+	/// The span of this group, including parentheses.
+	///
+	/// The end of the span is only known once the group has been fully iterated.
+	/// Until then it points past the last token that was read.
+	///
+	/// The span of the top level covers all data.
+	pub fn span(&self) -> Span {
+		self.span
+	}
+
+	/// The error that stopped iteration, if any.
+	///
+	/// This is shared by all iterators of the same [`Groups`].
+	pub fn error(&self) -> Option<Error> {
+		self.groups.error()
+	}
+
+	/// The data passed to [`parse2`].
+	pub fn data(&self) -> &'a [u8] {
+		self.groups.data
+	}
+
+	/// Whether there are no more items in this group.
+	///
+	/// This does not advance the iterator.
+	/// If the next token is malformed this returns `false`.
+	pub fn at_end(&self) -> bool {
+		let r = self.groups;
+		if self.done || r.error.get().is_some() {
+			return true;
+		}
+		let mut it = Iter {
+			data: r.data,
+			index: r.index.get(),
+			permissive: r.permissive,
+		};
+		match it.next_spanned() {
+			None => !self.nested,
+			Some(Ok((Token::End, _))) => self.nested,
+			_ => false,
+		}
+	}
+
+	/// Restart iteration at the first item this iterator returned.
+	///
+	/// Any iterators over groups inside this group must not be used afterwards.
+	/// This does nothing if an error occurred or no item was returned yet.
+	pub fn rewind(&mut self) {
+		let Some(start) = self.start.filter(|_| self.error().is_none()) else {
+			return;
+		};
+		self.groups.index.set(start);
+		self.done = false;
+	}
+
 	pub fn next_str(&mut self) -> Option<&'a str> {
 		self.next().and_then(|e| e.into_str())
 	}
@@ -113,30 +413,60 @@ impl<'a, 'b> Iterator for GroupsIter<'a, 'b> {
 	type Item = Item<'a, 'b>;
 
 	fn next(&mut self) -> Option<Self::Item> {
-		let r = self.inner?;
+		let r = self.groups;
+		if self.done || r.error.get().is_some() {
+			return None;
+		}
 		let mut it = Iter {
 			data: r.data,
 			index: r.index.get(),
+			permissive: r.permissive,
 		};
-		if (it.index as isize) < 0 {
-			return None;
-		}
-		let tk = it.next();
+		self.start.get_or_insert(it.index);
+		let tk = it.next_spanned();
 		r.index.set(it.index);
+		let error = |kind, offset| {
+			r.error.set(Some(Error::new(r.data, kind, offset)));
+			None
+		};
 		match tk {
+			None if self.nested => error(ErrorKind::UnclosedGroup, self.span.start),
 			None => None,
+			Some(Ok((Token::End, span))) if !self.nested => {
+				error(ErrorKind::UnmatchedClose, span.start)
+			}
 			Some(Err(e)) => {
-				r.index.set(e.into_num());
+				r.error.set(Some(e));
 				None
 			}
-			Some(Ok(tk)) => Some(match tk {
-				Token::Str(s) => Item::Str(s),
-				Token::Begin => Item::Group(Self { inner: self.inner }),
-				Token::End => {
-					self.inner = None;
-					return None
+			Some(Ok((tk, span))) => {
+				if self.nested {
+					self.span.end = span.end;
 				}
-			}),
+				Some(match tk {
+					Token::Str(text) => Item::Str(Atom {
+						text,
+						span,
+						quote: None,
+					}),
+					Token::Quoted(text, q) => Item::Str(Atom {
+						text,
+						span,
+						quote: Some(q),
+					}),
+					Token::Begin => Item::Group(Self {
+						groups: self.groups,
+						done: false,
+						nested: true,
+						span,
+						start: None,
+					}),
+					Token::End => {
+						self.done = true;
+						return None;
+					}
+				})
+			}
 		}
 	}
 }
@@ -149,14 +479,67 @@ impl Drop for GroupsIter<'_, '_> {
 
 impl core::iter::FusedIterator for GroupsIter<'_, '_> {}
 
+/// A string together with its location.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Atom<'a> {
+	/// The text of the string. For quoted strings this excludes the quotes
+	/// and escape sequences are not decoded.
+	pub text: &'a str,
+	pub span: Span,
+	/// The kind of quotes around the string, or `None` for a bare symbol.
+	pub quote: Option<Quote>,
+}
+
+impl<'a> Atom<'a> {
+	pub fn is_quoted(&self) -> bool {
+		self.quote.is_some()
+	}
+
+	/// Get the text with escape sequences decoded.
+	///
+	/// Bare symbols are returned as is.
+	/// See [`unescape_into`] for details.
+	pub fn unescape_into<'b>(&self, buf: &'b mut [u8]) -> Result<&'b str, Error>
+	where
+		'a: 'b,
+	{
+		match self.quote {
+			Some(_) => unescape_into(self.text, buf),
+			None => Ok(self.text),
+		}
+	}
+
+	/// Get the text with escape sequences decoded.
+	///
+	/// Bare symbols are returned as is.
+	#[cfg(feature = "alloc")]
+	pub fn unescape(&self) -> Result<alloc::borrow::Cow<'a, str>, Error> {
+		match self.quote {
+			Some(_) => unescape(self.text),
+			None => Ok(self.text.into()),
+		}
+	}
+}
+
 #[derive(Debug)]
 pub enum Item<'a, 'b> {
-	Str(&'a str),
+	Str(Atom<'a>),
 	Group(GroupsIter<'a, 'b>),
 }
 
 impl<'a, 'b> Item<'a, 'b> {
+	pub fn span(&self) -> Span {
+		match self {
+			Self::Str(s) => s.span,
+			Self::Group(g) => g.span(),
+		}
+	}
+
 	pub fn into_str(self) -> Option<&'a str> {
+		self.into_atom().map(|s| s.text)
+	}
+
+	pub fn into_atom(self) -> Option<Atom<'a>> {
 		match self {
 			Self::Str(s) => Some(s),
 			_ => None,
@@ -171,39 +554,93 @@ impl<'a, 'b> Item<'a, 'b> {
 	}
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub enum Error {
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
 	UnterminatedQuote,
 	InvalidSymbolChar,
 	InvalidUtf8,
+	InvalidEscape,
+	/// A `)` without a matching `(`.
+	UnmatchedClose,
+	/// A `(` without a matching `)`.
+	UnclosedGroup,
+	/// A symbol or string that doesn't fit in the buffer of a [`stream::Stream`].
+	AtomTooLong,
 }
 
-impl Error {
-	fn into_num(self) -> usize {
-		(match self {
-			Self::UnterminatedQuote => -1,
-			Self::InvalidSymbolChar => -2,
-			Self::InvalidUtf8 => -3,
-		}) as usize
-	}
-
-	fn from_num(n: usize) -> Option<Self> {
-		Some(match n as isize {
-			-1 => Self::UnterminatedQuote,
-			-2 => Self::InvalidSymbolChar,
-			-3 => Self::InvalidUtf8,
-			_ => return None,
+impl ErrorKind {
+	/// A description of what was expected at the position of the error.
+	pub fn expected(&self) -> &'static str {
+		match self {
+			Self::UnterminatedQuote => "closing quote",
+			Self::InvalidSymbolChar => "symbol character, whitespace or parenthesis",
+			Self::InvalidUtf8 => "valid UTF-8",
+			Self::InvalidEscape => "escape sequence",
+			Self::UnmatchedClose => "matching `(`",
+			Self::UnclosedGroup => "matching `)`",
+			Self::AtomTooLong => "a shorter symbol or string",
+		}
+	}
+}
+
+impl core::fmt::Display for ErrorKind {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.write_str(match self {
+			Self::UnterminatedQuote => "unterminated quote",
+			Self::InvalidSymbolChar => "invalid character in symbol",
+			Self::InvalidUtf8 => "invalid UTF-8",
+			Self::InvalidEscape => "invalid escape sequence",
+			Self::UnmatchedClose => "unmatched `)`",
+			Self::UnclosedGroup => "unclosed group",
+			Self::AtomTooLong => "symbol or string too long",
 		})
 	}
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Error {
+	pub kind: ErrorKind,
+	/// Byte offset of the error.
+	pub offset: usize,
+	pub line_col: LineCol,
+}
+
+impl Error {
+	fn new(data: &[u8], kind: ErrorKind, offset: usize) -> Self {
+		Self {
+			kind,
+			offset,
+			line_col: line_col(data, offset),
+		}
+	}
+}
+
+impl core::fmt::Display for Error {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		write!(
+			f,
+			"{}: {}, expected {}",
+			self.line_col,
+			self.kind,
+			self.kind.expected()
+		)
+	}
+}
+
+impl core::error::Error for Error {}
+
 #[deprecated(note = "use `parse2`, which is less error-prone")]
 pub fn parse<'a>(data: &'a [u8]) -> Iter<'a> {
-	Iter { data, index: 0 }
+	Iter::new(data)
 }
 
 pub fn parse2<'a>(data: &'a [u8]) -> Groups<'a> {
-	Groups { data, index: 0.into() }
+	Groups {
+		data,
+		index: 0.into(),
+		error: None.into(),
+		permissive: false,
+	}
 }
 
 #[cfg(test)]
@@ -227,15 +664,24 @@ mod test {
 		assert_eq!(it.next(), Some(Ok(Token::Str("1af4"))));
 		assert_eq!(it.next(), Some(Ok(Token::Begin)));
 		assert_eq!(it.next(), Some(Ok(Token::Str("1000"))));
-		assert_eq!(it.next(), Some(Ok(Token::Str("drivers/pci/virtio/net"))));
+		assert_eq!(
+			it.next(),
+			Some(Ok(Token::Quoted("drivers/pci/virtio/net", Quote::Double)))
+		);
 		assert_eq!(it.next(), Some(Ok(Token::End)));
 		assert_eq!(it.next(), Some(Ok(Token::Begin)));
 		assert_eq!(it.next(), Some(Ok(Token::Str("1001"))));
-		assert_eq!(it.next(), Some(Ok(Token::Str("drivers/pci/virtio/blk"))));
+		assert_eq!(
+			it.next(),
+			Some(Ok(Token::Quoted("drivers/pci/virtio/blk", Quote::Double)))
+		);
 		assert_eq!(it.next(), Some(Ok(Token::End)));
 		assert_eq!(it.next(), Some(Ok(Token::Begin)));
 		assert_eq!(it.next(), Some(Ok(Token::Str("1050"))));
-		assert_eq!(it.next(), Some(Ok(Token::Str("drivers/pci/virtio/gpu"))));
+		assert_eq!(
+			it.next(),
+			Some(Ok(Token::Quoted("drivers/pci/virtio/gpu", Quote::Double)))
+		);
 		assert_eq!(it.next(), Some(Ok(Token::End)));
 		assert_eq!(it.next(), Some(Ok(Token::End)));
 		assert_eq!(it.next(), Some(Ok(Token::Begin)));
@@ -244,7 +690,10 @@ mod test {
 		assert_eq!(it.next(), Some(Ok(Token::Str("1616"))));
 		assert_eq!(
 			it.next(),
-			Some(Ok(Token::Str("drivers/pci/intel/hd graphics")))
+			Some(Ok(Token::Quoted(
+				"drivers/pci/intel/hd graphics",
+				Quote::Double
+			)))
 		);
 		assert_eq!(it.next(), Some(Ok(Token::End)));
 		assert_eq!(it.next(), Some(Ok(Token::End)));
@@ -362,4 +811,201 @@ mod test {
 		}
 		assert!(cf.into_error().is_none());
 	}
+
+	#[test]
+	fn spans() {
+		let t = "(a \"bé\"\n\t(ç d))".as_bytes();
+		#[allow(deprecated)]
+		let mut it = parse(t);
+		let mut next = || it.next_spanned().unwrap().unwrap();
+		assert_eq!(next(), (Token::Begin, Span { start: 0, end: 1 }));
+		assert_eq!(next(), (Token::Str("a"), Span { start: 1, end: 2 }));
+		assert_eq!(
+			next(),
+			(
+				Token::Quoted("bé", Quote::Double),
+				Span { start: 3, end: 8 }
+			)
+		);
+		assert_eq!(next(), (Token::Begin, Span { start: 10, end: 11 }));
+		assert_eq!(next(), (Token::Str("ç"), Span { start: 11, end: 13 }));
+
+		assert_eq!(line_col(t, 0), LineCol { line: 1, column: 1 });
+		assert_eq!(line_col(t, 8), LineCol { line: 1, column: 8 });
+		assert_eq!(line_col(t, 11), LineCol { line: 2, column: 3 });
+		assert_eq!(line_col(t, 14), LineCol { line: 2, column: 5 });
+		assert_eq!(line_col(t, 100), LineCol { line: 2, column: 8 });
+
+		let mut cf = parse2(t);
+		{
+			let mut it = cf.iter();
+			let mut g = it.next_group().unwrap();
+			assert_eq!(g.span(), Span { start: 0, end: 1 });
+			let a = g.next().unwrap().into_atom().unwrap();
+			assert_eq!(a.span, Span { start: 1, end: 2 });
+			assert_eq!(g.next().unwrap().span(), Span { start: 3, end: 8 });
+			let mut g2 = g.next_group().unwrap();
+			assert_eq!(g2.next_str(), Some("ç"));
+			assert_eq!(g2.next_str(), Some("d"));
+			assert!(g2.next().is_none());
+			assert_eq!(g2.span(), Span { start: 10, end: 16 });
+			assert!(g.next().is_none());
+			assert_eq!(g.span(), Span { start: 0, end: 17 });
+		}
+		assert!(cf.into_error().is_none());
+	}
+
+	#[test]
+	fn errors() {
+		let t = b"(a\n  (b \"c))";
+		let mut cf = parse2(t);
+		{
+			let mut it = cf.iter();
+			let mut g = it.next_group().unwrap();
+			assert_eq!(g.next_str(), Some("a"));
+			let mut g2 = g.next_group().unwrap();
+			assert_eq!(g2.next_str(), Some("b"));
+			assert!(g2.next().is_none());
+			let e = g2.error().unwrap();
+			assert_eq!(e.kind, ErrorKind::UnterminatedQuote);
+			assert_eq!(e.offset, 8);
+			assert_eq!(e.line_col, LineCol { line: 2, column: 6 });
+			assert_eq!(
+				e.to_string(),
+				"2:6: unterminated quote, expected closing quote"
+			);
+			assert!(g.next().is_none());
+			assert!(it.next().is_none());
+		}
+		assert_eq!(cf.into_error().unwrap().offset, 8);
+
+		let t = b"(ab\xff)";
+		#[allow(deprecated)]
+		let mut it = parse(t);
+		assert_eq!(it.next(), Some(Ok(Token::Begin)));
+		let e = it.next().unwrap().unwrap_err();
+		assert_eq!(e.kind, ErrorKind::InvalidUtf8);
+		assert_eq!(e.offset, 3);
+	}
+
+	#[test]
+	fn escapes() {
+		let t = br#"("a\"b" 'c\'\n\t\r\0\\' "\x41\u{e9}\u{1F600}")"#;
+		#[allow(deprecated)]
+		let mut it = parse(t);
+		assert_eq!(it.next(), Some(Ok(Token::Begin)));
+		let mut buf = [0; 32];
+		let mut next = || it.next().unwrap().unwrap().into_str().unwrap();
+		assert_eq!(unescape_into(next(), &mut buf), Ok("a\"b"));
+		assert_eq!(unescape_into(next(), &mut buf), Ok("c'\n\t\r\0\\"));
+		assert_eq!(unescape_into(next(), &mut buf), Ok("Aé😀"));
+
+		for (t, offset) in [
+			(&br#"("\q")"#[..], 2),
+			(br#"(a "b\x80")"#, 5),
+			(br#"("\u{}")"#, 2),
+			(br#"("\u{110000}")"#, 2),
+			(br#"("\u{1234567}")"#, 2),
+			(br#"("\x4")"#, 2),
+			(br#"("\x+1")"#, 2),
+			(br#"("\u{+1}")"#, 2),
+		] {
+			let mut cf = parse2(t);
+			for _ in cf.iter() {}
+			let e = cf.into_error().unwrap();
+			assert_eq!(e.kind, ErrorKind::InvalidEscape);
+			assert_eq!(e.offset, offset);
+		}
+
+		let e = unescape_into("\\x+1", &mut buf).unwrap_err();
+		assert_eq!((e.kind, e.offset), (ErrorKind::InvalidEscape, 0));
+		let e = unescape_into("ab\\c\\", &mut buf).unwrap_err();
+		assert_eq!((e.kind, e.offset), (ErrorKind::InvalidEscape, 2));
+	}
+
+	#[cfg(feature = "alloc")]
+	#[test]
+	fn escapes_cow() {
+		use std::borrow::Cow;
+		assert!(matches!(unescape("abc"), Ok(Cow::Borrowed("abc"))));
+		assert_eq!(unescape("a\\u{62}c").unwrap(), "abc");
+		assert!(unescape("\\").is_err());
+	}
+
+	#[test]
+	fn quotes() {
+		let t = br#"(1000 "1000" '10\x30')"#;
+		let mut cf = parse2(t);
+		{
+			let mut it = cf.iter();
+			let mut g = it.next_group().unwrap();
+			let mut next = || g.next().unwrap().into_atom().unwrap();
+			let mut buf = [0; 8];
+			let a = next();
+			assert_eq!((a.text, a.quote), ("1000", None));
+			assert_eq!(a.unescape_into(&mut buf), Ok("1000"));
+			let a = next();
+			assert_eq!((a.text, a.quote), ("1000", Some(Quote::Double)));
+			let a = next();
+			assert_eq!((a.text, a.quote), ("10\\x30", Some(Quote::Single)));
+			assert_eq!(a.unescape_into(&mut buf), Ok("100"));
+		}
+		assert!(cf.into_error().is_none());
+	}
+
+	#[test]
+	fn unbalanced() {
+		let mut cf = parse2(b"(a (b))\n) (c)");
+		{
+			let mut it = cf.iter();
+			assert!(it.next_group().is_some());
+			assert!(it.next().is_none());
+			let e = it.error().unwrap();
+			assert_eq!(e.kind, ErrorKind::UnmatchedClose);
+			assert_eq!(e.line_col, LineCol { line: 2, column: 1 });
+		}
+		assert_eq!(cf.into_error().unwrap().offset, 8);
+
+		let mut cf = parse2(b"(a (b c)\n\t(d)");
+		{
+			let mut it = cf.iter();
+			let mut g = it.next_group().unwrap();
+			assert_eq!(g.next_str(), Some("a"));
+			assert!(g.next_group().is_some());
+			assert!(g.next_group().is_some());
+			assert!(g.next().is_none());
+			assert_eq!(g.error().unwrap().kind, ErrorKind::UnclosedGroup);
+		}
+		assert_eq!(cf.into_error().unwrap().offset, 0);
+	}
+
+	#[test]
+	fn symbol_chars() {
+		for (t, offset) in [
+			(&b"(foo\"bar)"[..], 4),
+			(b"(a b'c)", 4),
+			(b"(a\x07)", 2),
+			(b"(\\a)", 1),
+			(b"(a;b)", 2),
+		] {
+			let mut cf = parse2(t);
+			for _ in cf.iter() {}
+			let e = cf.into_error().unwrap();
+			assert_eq!(e.kind, ErrorKind::InvalidSymbolChar);
+			assert_eq!(e.offset, offset);
+
+			let mut cf = parse2(t).permissive();
+			for _ in cf.iter() {}
+			assert!(cf.into_error().is_none());
+		}
+
+		#[allow(deprecated)]
+		let mut it = parse(b"foo\"bar").permissive();
+		assert_eq!(it.next(), Some(Ok(Token::Str("foo\"bar"))));
+
+		assert!(is_symbol("drivers/pci/virtio/net"));
+		assert!(is_symbol("ç"));
+		assert!(!is_symbol(""));
+		assert!(!is_symbol("hd graphics"));
+	}
 }
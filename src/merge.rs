@@ -0,0 +1,409 @@
+//! Combine layers of configuration by head symbol.
+//!
+//! Each layer is merged into the result of merging the layers below it.
+//! A group in a layer is matched with the first group with the same head at the same place
+//! in the lower layers, and the [`Rule`] for its path decides how they are combined.
+//! Groups without a match are added after the existing items.
+//!
+//! A group whose only item after the head is the delete marker, `!delete` by default,
+//! removes all groups with that head at that place from the lower layers.
+//!
+//! ```
+//! use scf::{merge::{Merger, Rule}, Document};
+//!
+//! let base = Document::parse(b"(net (dhcp yes) (dns 1.1.1.1)) (modules virtio-net) (debug)").unwrap();
+//! let machine = Document::parse(b"(net (dns 9.9.9.9)) (modules e1000) (debug !delete)").unwrap();
+//! let doc = Merger::new()
+//!     .rule("modules", Rule::Append)
+//!     .merge([base, machine]);
+//! assert_eq!(doc.to_string(), "(net (dhcp yes) (dns 9.9.9.9))\n(modules virtio-net e1000)\n");
+//! ```
+
+use crate::{Document, KeyPath, List, Value};
+use alloc::{borrow::ToOwned, string::String, vec, vec::Vec};
+use core::fmt;
+
+/// How a group is combined with the matching group of a lower layer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Rule {
+	/// Merge groups with the same head recursively.
+	///
+	/// If the group has any other items after its head, such as symbols, strings or groups
+	/// without a head, they replace those of the lower group in its place.
+	/// That way `(timeout 10)` overrides `(timeout 5)`.
+	#[default]
+	Merge,
+	/// Replace the lower group.
+	Replace,
+	/// Add the items after the head to the end of the lower group.
+	Append,
+}
+
+/// Merges layers according to a set of rules.
+#[derive(Clone, Debug)]
+pub struct Merger {
+	rules: Vec<(Vec<String>, Rule)>,
+	delete: String,
+}
+
+impl Default for Merger {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Merger {
+	/// A merger that uses [`Rule::Merge`] for all groups.
+	pub fn new() -> Self {
+		Self {
+			rules: Vec::new(),
+			delete: "!delete".into(),
+		}
+	}
+
+	/// Use `rule` for the groups at `path`.
+	///
+	/// If several rules have the same path the last one is used.
+	pub fn rule<P: KeyPath>(mut self, path: P, rule: Rule) -> Self {
+		let path = (0..)
+			.map_while(|i| path.segment(i))
+			.map(str::to_owned)
+			.collect();
+		self.rules.push((path, rule));
+		self
+	}
+
+	/// Use a different symbol to delete groups.
+	pub fn delete_marker(mut self, marker: impl Into<String>) -> Self {
+		self.delete = marker.into();
+		self
+	}
+
+	/// Merge `layers`, from lowest to highest.
+	pub fn merge<'a, I: IntoIterator<Item = Document<'a>>>(&self, layers: I) -> Document<'a> {
+		let mut doc = Document::default();
+		for layer in layers {
+			self.merge_into(&mut doc, layer);
+		}
+		doc
+	}
+
+	/// Merge `overlay` into `base`.
+	pub fn merge_into<'a>(&self, base: &mut Document<'a>, overlay: Document<'a>) {
+		self.merge_items(&mut Vec::new(), &mut base.root, 0, overlay.root.items);
+	}
+
+	/// The groups at `path` in every layer and the group that results from merging them.
+	///
+	/// Use [`Document::set_origin`] or [`include`](crate::include) to show
+	/// where each group was read from.
+	pub fn explain<'a, P: KeyPath>(&self, layers: &[Document<'a>], path: P) -> Explain<'a> {
+		let mut entries = Vec::new();
+		for (layer, doc) in layers.iter().enumerate() {
+			for g in doc.lookup_all(&path) {
+				entries.push(Entry {
+					layer,
+					group: g.clone(),
+					used: false,
+				});
+			}
+		}
+		let merged = self.merge(layers.iter().cloned());
+		let result = merged.lookup(&path).cloned();
+		if let Some(r) = &result {
+			for e in &mut entries {
+				e.used = contributes(&e.group, r);
+			}
+		}
+		let segments = (0..).map_while(|i| path.segment(i));
+		Explain {
+			path: segments.collect::<Vec<_>>().join("/"),
+			entries,
+			result,
+		}
+	}
+
+	fn rule_for(&self, path: &[String]) -> Rule {
+		let r = self.rules.iter().rev().find(|(p, _)| p == path);
+		r.map_or(Rule::Merge, |(_, r)| *r)
+	}
+
+	fn is_delete(&self, l: &List<'_>) -> bool {
+		matches!(&l.items[..], [_, Value::Atom(t)] if t.quote.is_none() && t.text == self.delete)
+	}
+
+	/// Merge `items` into those of `base` after the first `start`.
+	fn merge_items<'a>(
+		&self,
+		path: &mut Vec<String>,
+		base: &mut List<'a>,
+		start: usize,
+		items: Vec<Value<'a>>,
+	) {
+		// Items of this layer at or after `lower` aren't matched.
+		let mut lower = base.items.len();
+		// Where to insert the next item that isn't a group with a head.
+		let mut at = None;
+		for v in items {
+			let g = match v {
+				Value::List(g) if g.head().is_some() => g,
+				v => {
+					let i = *at.get_or_insert_with(|| {
+						let first = base.items[start..lower]
+							.iter()
+							.position(|v| v.head().is_none());
+						lower -= retain(base, start, lower, |v| v.head().is_some());
+						first.map_or(base.items.len(), |i| i + start)
+					});
+					base.items.insert(i, v);
+					at = Some(i + 1);
+					if i < lower {
+						lower += 1;
+					}
+					continue;
+				}
+			};
+			let h = g.head().expect("groups have a head");
+			if self.is_delete(&g) {
+				lower -= retain(base, start, lower, |v| v.head() != Some(h));
+				continue;
+			}
+			path.push(h.to_owned());
+			let found = base.items[start..lower]
+				.iter()
+				.position(|v| v.head() == Some(h))
+				.map(|i| i + start);
+			match (found, self.rule_for(path)) {
+				(Some(i), Rule::Merge) => {
+					let Value::List(b) = &mut base.items[i] else {
+						unreachable!("groups have a head")
+					};
+					self.merge_items(path, b, 1, g.items.into_iter().skip(1).collect());
+				}
+				(Some(i), Rule::Replace) => base.items[i] = self.fresh(path, g).into(),
+				(Some(i), Rule::Append) => {
+					let Value::List(b) = &mut base.items[i] else {
+						unreachable!("groups have a head")
+					};
+					b.items
+						.extend(self.fresh(path, g).items.into_iter().skip(1));
+				}
+				(None, _) => base.items.push(self.fresh(path, g).into()),
+			}
+			path.pop();
+		}
+	}
+
+	/// Merge `g` into an empty group, which removes delete markers.
+	fn fresh<'a>(&self, path: &mut Vec<String>, mut g: List<'a>) -> List<'a> {
+		let items = g.items.split_off(1);
+		let mut l = List {
+			items: vec![g.items.remove(0)],
+			span: g.span,
+			origin: g.origin,
+		};
+		self.merge_items(path, &mut l, 1, items);
+		l
+	}
+}
+
+/// Remove the items of `l` in `start..end` for which `f` returns `false`
+/// and return how many were removed.
+fn retain(
+	l: &mut List<'_>,
+	start: usize,
+	end: usize,
+	mut f: impl FnMut(&Value<'_>) -> bool,
+) -> usize {
+	let len = l.items.len();
+	let mut i = 0;
+	l.items.retain(|v| {
+		i += 1;
+		!(start..end).contains(&(i - 1)) || f(v)
+	});
+	len - l.items.len()
+}
+
+/// Whether any item after the head of `g` ended up in `result`, a group with the same head.
+fn contributes(g: &List<'_>, result: &List<'_>) -> bool {
+	if g.tail().is_empty() {
+		return g.span == result.span && g.origin == result.origin;
+	}
+	g.tail().iter().any(|v| {
+		result.tail().iter().any(|r| match (v, r) {
+			(Value::List(a), Value::List(b)) if a.head().is_some() && a.head() == b.head() => {
+				contributes(a, b)
+			}
+			_ => v == r && v.span() == r.span() && v.origin() == r.origin(),
+		})
+	})
+}
+
+/// How the groups at a path were merged. See [`Merger::explain`].
+#[derive(Clone, Debug)]
+pub struct Explain<'a> {
+	/// The path, with segments separated by `/`.
+	pub path: String,
+	/// The groups at the path, from the lowest layer to the highest.
+	pub entries: Vec<Entry<'a>>,
+	/// The first group at the path after merging, if any.
+	pub result: Option<List<'a>>,
+}
+
+impl<'a> Explain<'a> {
+	/// The highest entry with items in the result.
+	pub fn winner(&self) -> Option<&Entry<'a>> {
+		self.entries.iter().rev().find(|e| e.used)
+	}
+}
+
+/// Lists each entry, marking the winner with `*` and other entries
+/// with items in the result with `+`, followed by the result.
+impl fmt::Display for Explain<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "{}", self.path)?;
+		let winner = self.entries.iter().rposition(|e| e.used);
+		for (i, e) in self.entries.iter().enumerate() {
+			let mark = match e.used {
+				_ if winner == Some(i) => '*',
+				true => '+',
+				false => ' ',
+			};
+			write!(f, "{} layer {}", mark, e.layer)?;
+			if let Some(o) = &e.group.origin {
+				write!(f, " {}", o)?;
+			}
+			writeln!(f, ": {}", e.group)?;
+		}
+		match &self.result {
+			Some(r) => writeln!(f, "= {}", r),
+			None => writeln!(f, "= (none)"),
+		}
+	}
+}
+
+/// A group at the explained path in one layer.
+#[derive(Clone, Debug)]
+pub struct Entry<'a> {
+	/// The index of the layer.
+	pub layer: usize,
+	pub group: List<'a>,
+	/// Whether any of the items of the group are in the result.
+	pub used: bool,
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::fixture::doc;
+	use alloc::string::ToString;
+
+	#[test]
+	fn layers() {
+		let base = doc("(pci-drivers
+	(1af4
+		(1000 \"drivers/pci/virtio/net\")
+		(1001 \"drivers/pci/virtio/blk\"))
+	(8086 (1616 \"drivers/pci/intel/hd_graphics\")))
+(modules virtio-net virtio-blk)
+(console ttyS0 115200)");
+		let machine = doc("(pci-drivers
+	(1af4 (1001 \"drivers/pci/virtio/blk-legacy\") (1040 \"drivers/pci/virtio/gpu\"))
+	(10de (1eb8 \"drivers/pci/nvidia\")))
+(modules e1000)
+(console tty0)");
+		let boot = doc("(pci-drivers (8086 !delete) (10de (1eb8 !delete)))
+(modules (debug yes))");
+		let m = Merger::new()
+			.rule("modules", Rule::Append)
+			.rule(["pci-drivers", "10de"], Rule::Replace);
+		let doc = m.merge([base, machine, boot]);
+		assert_eq!(
+			doc.to_string(),
+			"(pci-drivers \
+			(1af4 (1000 \"drivers/pci/virtio/net\") (1001 \"drivers/pci/virtio/blk-legacy\") \
+			(1040 \"drivers/pci/virtio/gpu\")) \
+			(10de))\n\
+			(modules virtio-net virtio-blk e1000 (debug yes))\n\
+			(console tty0)\n"
+		);
+		assert_eq!(
+			doc.lookup("pci-drivers/1af4/1001").unwrap()[1].as_str(),
+			Some("drivers/pci/virtio/blk-legacy")
+		);
+	}
+
+	#[test]
+	fn rules() {
+		let base = || doc("(a (x 1) (y 2) z) (a (x 3))");
+		let m = Merger::new();
+		assert_eq!(
+			m.merge([base(), doc("(a (x 4))")]).to_string(),
+			"(a (x 4) (y 2) z)\n(a (x 3))\n"
+		);
+		assert_eq!(
+			m.merge([base(), doc("(a w (y 5 !delete))")]).to_string(),
+			"(a (x 1) (y 5 !delete) w)\n(a (x 3))\n"
+		);
+		let m = Merger::new().rule("a", Rule::Replace);
+		assert_eq!(
+			m.merge([base(), doc("(a (x 4))")]).to_string(),
+			"(a (x 4))\n(a (x 3))\n"
+		);
+		let m = Merger::new().rule("a", Rule::Append).delete_marker("-");
+		assert_eq!(
+			m.merge([base(), doc("(a (x 4) (y -)) (b (c -) d)")])
+				.to_string(),
+			"(a (x 1) (y 2) z (x 4))\n(a (x 3))\n(b d)\n"
+		);
+		assert_eq!(m.merge([base(), doc("(a -)")]).to_string(), "");
+		// Delete markers in the lowest layer have nothing to delete.
+		assert_eq!(
+			Merger::new().merge([doc("(a !delete) (b)")]).to_string(),
+			"(b)\n"
+		);
+	}
+
+	#[test]
+	fn explain() {
+		let layers = [
+			(
+				"base",
+				"(console ttyS0 115200)\n(net\n  (dns 1.1.1.1)\n  (dhcp yes))\n(modules virtio-net)",
+			),
+			("machine", "(console tty0)\n(modules e1000)"),
+			("boot", "(net (dns 9.9.9.9))\n(modules !delete)"),
+		];
+		let layers = layers.map(|(name, s)| {
+			let mut d = doc(s);
+			d.set_origin(name, s.as_bytes());
+			d
+		});
+		let m = Merger::new().rule("modules", Rule::Append);
+		let e = m.explain(&layers, "console");
+		assert_eq!(e.winner().unwrap().layer, 1);
+		assert_eq!(
+			e.to_string(),
+			"console\n  layer 0 base:1:1: (console ttyS0 115200)\n\
+			* layer 1 machine:1:1: (console tty0)\n= (console tty0)\n"
+		);
+		let e = m.explain(&layers, "net/dns");
+		assert_eq!(
+			e.to_string(),
+			"net/dns\n  layer 0 base:3:3: (dns 1.1.1.1)\n\
+			* layer 2 boot:1:6: (dns 9.9.9.9)\n= (dns 9.9.9.9)\n"
+		);
+		let e = m.explain(&layers, "net");
+		assert_eq!(
+			e.entries.iter().map(|e| e.used).collect::<Vec<_>>(),
+			[true, true]
+		);
+		let e = m.explain(&layers, "modules");
+		assert!(e.winner().is_none());
+		assert!(e.to_string().ends_with("= (none)\n"));
+
+		let merged = m.merge(layers);
+		let dns = &merged.lookup("net/dns").unwrap().items[1];
+		assert_eq!(dns.origin().unwrap().to_string(), "boot:1:11");
+	}
+}
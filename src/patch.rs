@@ -0,0 +1,561 @@
+//! Patches written in S syntax, generated from a [`diff`](crate::diff).
+//!
+//! A patch is a sequence of operations, each addressing a group by its path.
+//! A path is written as `(path step...)`, where each step is the head of a group,
+//! or `(head index)` to select a later group with the same head. See [`Step`].
+//! `(path)` is the top level of the document.
+//!
+//! - `(add path group)` adds the group at the end of the group at the parent path.
+//!   The path must be that of the new group.
+//! - `(remove path group)` removes the group at the path, which must be equal to `group`.
+//! - `(replace path old new)` replaces the group at the path, which must be equal to `old`.
+//!   For the top level `old` and `new` contain the items of the document.
+//! - `(move from to)` moves the group at `from` to the end of the parent of `to`.
+//!   `to` must be the path of the group after moving it.
+//!
+//! Operations are applied in order, so paths refer to the document
+//! as modified by the previous operations.
+//!
+//! ```
+//! use scf::{patch::Patch, Document};
+//!
+//! let old = Document::parse(b"(pci-drivers (1af4 (1000 net)) (8086 (1616 gpu)))").unwrap();
+//! let new = Document::parse(b"(pci-drivers (1af4 (1000 net) (1616 gpu)) (8086))").unwrap();
+//! let patch = Patch::generate(&old, &new);
+//! assert_eq!(patch.to_string(), "\
+//! (move (path pci-drivers 8086 1616) (path pci-drivers 1af4 1616))
+//! ");
+//!
+//! let mut doc = old.clone();
+//! patch.apply(&mut doc).unwrap();
+//! assert_eq!(doc, new);
+//! ```
+
+use crate::{
+	diff::{self, ChangeKind, Step},
+	Document, List, Span, Value,
+};
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Op<'d, 'a> {
+	Add {
+		path: Vec<Step<'d>>,
+		group: &'d List<'a>,
+	},
+	Remove {
+		path: Vec<Step<'d>>,
+		group: &'d List<'a>,
+	},
+	Replace {
+		path: Vec<Step<'d>>,
+		old: &'d List<'a>,
+		new: &'d List<'a>,
+	},
+	Move {
+		from: Vec<Step<'d>>,
+		to: Vec<Step<'d>>,
+	},
+}
+
+impl<'d> Op<'d, '_> {
+	/// The path of the group the operation applies to.
+	pub fn path(&self) -> &[Step<'d>] {
+		match self {
+			Self::Add { path, .. } | Self::Remove { path, .. } | Self::Replace { path, .. } => path,
+			Self::Move { from, .. } => from,
+		}
+	}
+}
+
+impl fmt::Display for Op<'_, '_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Add { path, group } => write!(f, "(add {} {})", PathFmt(path), group),
+			Self::Remove { path, group } => write!(f, "(remove {} {})", PathFmt(path), group),
+			Self::Replace { path, old, new } => {
+				write!(f, "(replace {} {} {})", PathFmt(path), old, new)
+			}
+			Self::Move { from, to } => write!(f, "(move {} {})", PathFmt(from), PathFmt(to)),
+		}
+	}
+}
+
+/// Writes a path as `(path step...)`.
+struct PathFmt<'p, 'd>(&'p [Step<'d>]);
+
+impl fmt::Display for PathFmt<'_, '_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("(path")?;
+		for s in self.0 {
+			f.write_str(" ")?;
+			if s.index > 0 {
+				f.write_str("(")?;
+				crate::write_atom(f, s.head)?;
+				write!(f, " {})", s.index)?;
+			} else {
+				crate::write_atom(f, s.head)?;
+			}
+		}
+		f.write_str(")")
+	}
+}
+
+/// Writes a path as in [`diff::Change`].
+struct SlashPath<'p, 'd>(&'p [Step<'d>]);
+
+impl fmt::Display for SlashPath<'_, '_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for (i, s) in self.0.iter().enumerate() {
+			if i > 0 {
+				f.write_str("/")?;
+			}
+			s.fmt(f)?;
+		}
+		Ok(())
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PatchErrorKind {
+	/// An operation in the patch is malformed.
+	Invalid,
+	/// There is no group at the path, or at the parent path of an added group.
+	NotFound,
+	/// A group already exists at the path of an added or moved group.
+	Exists,
+	/// The group at the path differs from the one in the patch.
+	Mismatch,
+}
+
+impl fmt::Display for PatchErrorKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Invalid => "invalid operation",
+			Self::NotFound => "no such group",
+			Self::Exists => "group already exists",
+			Self::Mismatch => "group differs from the patch",
+		})
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PatchError {
+	pub kind: PatchErrorKind,
+	/// The index of the failed operation.
+	pub op: usize,
+	/// The path of the group that caused the error, if known.
+	pub path: String,
+	/// The span of the operation in the patch, if it was parsed.
+	pub span: Option<Span>,
+}
+
+impl PatchError {
+	fn new(kind: PatchErrorKind, op: usize, path: &[Step<'_>]) -> Self {
+		use fmt::Write;
+		let mut p = String::new();
+		let _ = write!(p, "{}", SlashPath(path));
+		Self {
+			kind,
+			op,
+			path: p,
+			span: None,
+		}
+	}
+}
+
+impl fmt::Display for PatchError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "operation {}", self.op)?;
+		if !self.path.is_empty() {
+			write!(f, " at `{}`", self.path)?;
+		}
+		write!(f, ": {}", self.kind)
+	}
+}
+
+impl core::error::Error for PatchError {}
+
+/// A sequence of operations.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Patch<'d, 'a> {
+	pub ops: Vec<Op<'d, 'a>>,
+}
+
+impl<'d, 'a> Patch<'d, 'a> {
+	/// The operations that turn `old` into `new`.
+	///
+	/// A group that was removed in one place and added unchanged in another is moved.
+	pub fn generate(old: &'d List<'a>, new: &'d List<'a>) -> Self {
+		let changes = diff::diff(old, new);
+		let is_replaced = |i: usize| {
+			changes[..i]
+				.iter()
+				.any(|c| c.kind == ChangeKind::Changed && changes[i].path.starts_with(&c.path))
+		};
+		let live = (0..changes.len())
+			.filter(|&i| !is_replaced(i))
+			.collect::<Vec<_>>();
+		// The removed change to turn into a move for each added change.
+		let mut moves = Vec::new();
+		for &a in live
+			.iter()
+			.filter(|&&i| changes[i].kind == ChangeKind::Added)
+		{
+			let r = live.iter().copied().find(|&r| {
+				changes[r].kind == ChangeKind::Removed
+					&& changes[r].old == changes[a].new
+					&& !moves.iter().any(|&(_, m)| m == r)
+			});
+			if let Some(r) = r {
+				moves.push((a, r));
+			}
+		}
+		// Removed groups by parent path, head and original index.
+		let mut removed: Vec<(Vec<Step<'d>>, Step<'d>)> = Vec::new();
+		let mut remove = |path: &[Step<'d>]| {
+			let (last, parent) = path.split_last().expect("removed groups have a path");
+			let before = removed
+				.iter()
+				.filter(|(p, s)| *p == parent && s.head == last.head && s.index < last.index)
+				.count();
+			removed.push((parent.to_vec(), *last));
+			let mut path = path.to_vec();
+			path.last_mut().unwrap().index -= before;
+			path
+		};
+		let mut ops = Vec::new();
+		for i in live {
+			let c = &changes[i];
+			match c.kind {
+				ChangeKind::Changed => ops.push(Op::Replace {
+					path: c.path.clone(),
+					old: c.old.unwrap(),
+					new: c.new.unwrap(),
+				}),
+				ChangeKind::Removed if moves.iter().any(|&(_, r)| r == i) => {}
+				ChangeKind::Removed => ops.push(Op::Remove {
+					path: remove(&c.path),
+					group: c.old.unwrap(),
+				}),
+				ChangeKind::Added => match moves.iter().find(|&&(a, _)| a == i) {
+					Some(&(_, r)) => ops.push(Op::Move {
+						from: remove(&changes[r].path),
+						to: c.path.clone(),
+					}),
+					None => ops.push(Op::Add {
+						path: c.path.clone(),
+						group: c.new.unwrap(),
+					}),
+				},
+			}
+		}
+		Self { ops }
+	}
+
+	/// Read a patch from its top-level groups.
+	pub fn parse(doc: &'d Document<'a>) -> Result<Self, PatchError> {
+		let mut ops = Vec::new();
+		for (i, v) in doc.iter().enumerate() {
+			let invalid = || PatchError {
+				span: Some(v.span()),
+				..PatchError::new(PatchErrorKind::Invalid, i, &[])
+			};
+			let l = v.as_list().ok_or_else(invalid)?;
+			let group = |v: &'d Value<'a>| v.as_list().filter(|l| l.head().is_some());
+			let op = match (l.head(), l.tail()) {
+				(Some("add"), [p, g]) => {
+					let (path, g) = (parse_path(p).ok_or_else(invalid)?, group(g));
+					let g = g.filter(|g| path.last().map(|s| s.head) == g.head());
+					Op::Add {
+						path,
+						group: g.ok_or_else(invalid)?,
+					}
+				}
+				(Some("remove"), [p, g]) => Op::Remove {
+					path: parse_path(p)
+						.filter(|p| !p.is_empty())
+						.ok_or_else(invalid)?,
+					group: group(g).ok_or_else(invalid)?,
+				},
+				(Some("replace"), [p, old, new]) => {
+					let path = parse_path(p).ok_or_else(invalid)?;
+					let list = |v: &'d Value<'a>| match path.is_empty() {
+						true => v.as_list(),
+						false => group(v),
+					};
+					Op::Replace {
+						old: list(old).ok_or_else(invalid)?,
+						new: list(new).ok_or_else(invalid)?,
+						path,
+					}
+				}
+				(Some("move"), [from, to]) => {
+					let from = parse_path(from).filter(|p| !p.is_empty());
+					let to = parse_path(to).filter(|p| !p.is_empty());
+					let (from, to) = from.zip(to).ok_or_else(invalid)?;
+					if from.last().map(|s| s.head) != to.last().map(|s| s.head) {
+						return Err(invalid());
+					}
+					Op::Move { from, to }
+				}
+				_ => return Err(invalid()),
+			};
+			ops.push(op);
+		}
+		Ok(Self { ops })
+	}
+
+	/// Apply the operations to `doc`.
+	///
+	/// If any operation fails, `doc` is left unchanged.
+	pub fn apply<'t>(&self, doc: &mut Document<'t>) -> Result<(), PatchError>
+	where
+		'a: 't,
+	{
+		let mut root = doc.root.clone();
+		for (i, op) in self.ops.iter().enumerate() {
+			apply(&mut root, op).map_err(|(kind, path)| PatchError::new(kind, i, path))?;
+		}
+		doc.root = root;
+		Ok(())
+	}
+}
+
+/// One operation per line.
+impl fmt::Display for Patch<'_, '_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for op in &self.ops {
+			writeln!(f, "{}", op)?;
+		}
+		Ok(())
+	}
+}
+
+fn parse_path<'d>(v: &'d Value<'_>) -> Option<Vec<Step<'d>>> {
+	let l = v.as_list().filter(|l| l.head() == Some("path"))?;
+	l.tail()
+		.iter()
+		.map(|s| match s {
+			Value::Atom(t) => Some(Step {
+				head: t.as_str(),
+				index: 0,
+			}),
+			Value::List(l) => match &l.items[..] {
+				[Value::Atom(head), Value::Atom(index)] => Some(Step {
+					head: head.as_str(),
+					index: index.as_str().parse().ok()?,
+				}),
+				_ => None,
+			},
+		})
+		.collect()
+}
+
+type OpError<'p, 'd> = (PatchErrorKind, &'p [Step<'d>]);
+
+fn apply<'p, 'd, 't>(root: &mut List<'t>, op: &'p Op<'d, 't>) -> Result<(), OpError<'p, 'd>> {
+	let not_found = |p| (PatchErrorKind::NotFound, p);
+	match op {
+		Op::Add { path, group } => {
+			let (last, parent) = path
+				.split_last()
+				.ok_or((PatchErrorKind::Invalid, &path[..]))?;
+			let parent = find(root, parent).ok_or(not_found(&path[..]))?;
+			insert(parent, last, (*group).clone()).map_err(|k| (k, &path[..]))
+		}
+		Op::Remove { path, group } => {
+			let (parent, i) = locate(root, path).ok_or(not_found(&path[..]))?;
+			if parent.items[i] != Value::List((*group).clone()) {
+				return Err((PatchErrorKind::Mismatch, path));
+			}
+			parent.items.remove(i);
+			Ok(())
+		}
+		Op::Replace { path, old, new } if path.is_empty() => {
+			if root.items != old.items {
+				return Err((PatchErrorKind::Mismatch, path));
+			}
+			root.items = new.items.clone();
+			Ok(())
+		}
+		Op::Replace { path, old, new } => {
+			let (parent, i) = locate(root, path).ok_or(not_found(&path[..]))?;
+			if parent.items[i] != Value::List((*old).clone()) {
+				return Err((PatchErrorKind::Mismatch, path));
+			}
+			parent.items[i] = Value::List((*new).clone());
+			Ok(())
+		}
+		Op::Move { from, to } => {
+			let (parent, i) = locate(root, from).ok_or(not_found(&from[..]))?;
+			let g = parent.items.remove(i);
+			let (last, to_parent) = to.split_last().ok_or((PatchErrorKind::Invalid, &to[..]))?;
+			let to_parent = find(root, to_parent).ok_or(not_found(&to[..]))?;
+			let Value::List(g) = g else {
+				unreachable!("steps match groups")
+			};
+			insert(to_parent, last, g).map_err(|k| (k, &to[..]))
+		}
+	}
+}
+
+/// Add `g` to the end of `parent` if that gives it the step `last`.
+fn insert<'t>(parent: &mut List<'t>, last: &Step<'_>, g: List<'t>) -> Result<(), PatchErrorKind> {
+	let count = parent
+		.items
+		.iter()
+		.filter(|v| v.head() == Some(last.head))
+		.count();
+	match count.cmp(&last.index) {
+		core::cmp::Ordering::Equal => {
+			parent.items.push(Value::List(g));
+			Ok(())
+		}
+		core::cmp::Ordering::Less => Err(PatchErrorKind::NotFound),
+		core::cmp::Ordering::Greater => Err(PatchErrorKind::Exists),
+	}
+}
+
+/// The group at `path`.
+fn find<'l, 't>(mut l: &'l mut List<'t>, path: &[Step<'_>]) -> Option<&'l mut List<'t>> {
+	for s in path {
+		let i = index(l, s)?;
+		l = l.items[i].as_list_mut()?;
+	}
+	Some(l)
+}
+
+/// The parent of the group at `path` and the index of the group in it.
+fn locate<'l, 't>(root: &'l mut List<'t>, path: &[Step<'_>]) -> Option<(&'l mut List<'t>, usize)> {
+	let (last, parent) = path.split_last()?;
+	let parent = find(root, parent)?;
+	let i = index(parent, last)?;
+	Some((parent, i))
+}
+
+/// The index in `l` of the group at step `s`.
+fn index(l: &List<'_>, s: &Step<'_>) -> Option<usize> {
+	let mut it = l.items.iter().enumerate();
+	let mut groups = it.by_ref().filter(|(_, v)| v.head() == Some(s.head));
+	groups.nth(s.index).map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::fixture::doc;
+	use alloc::string::ToString;
+
+	fn roundtrip(old: &str, new: &str, expected: &str) {
+		let (old, new) = (doc(old), doc(new));
+		let patch = Patch::generate(&old, &new);
+		let s = patch.to_string();
+		assert_eq!(s, expected);
+		let parsed = doc(&s);
+		let patch = Patch::parse(&parsed).unwrap();
+		let mut d = old.clone();
+		patch.apply(&mut d).unwrap();
+		// Reordering groups is not a change.
+		assert!(diff::diff(&d, &new).is_empty());
+	}
+
+	#[test]
+	fn generate() {
+		roundtrip(
+			"(pci-drivers
+	(1af4 (1000 net) (1001 blk))
+	(8086 (1616 gpu)))
+(modules a b)",
+			"(modules b a)
+(pci-drivers
+	(1af4 (1001 blk) (1000 net-legacy) (1040 gpu))
+	(1af4 (1000 net))
+	(10de))",
+			"(replace (path pci-drivers 1af4 1000) (1000 net) (1000 net-legacy))\n\
+			(add (path pci-drivers 1af4 1040) (1040 gpu))\n\
+			(remove (path pci-drivers 8086) (8086 (1616 gpu)))\n\
+			(add (path pci-drivers (1af4 1)) (1af4 (1000 net)))\n\
+			(add (path pci-drivers 10de) (10de))\n\
+			(replace (path modules) (modules a b) (modules b a))\n",
+		);
+		roundtrip(
+			"(x 1) (x 2) (x 3) (x 4) (y (x 5))",
+			"(x 1) (x 3) (z (x 4))",
+			"(replace (path (x 1)) (x 2) (x 3))\n\
+			(remove (path (x 2)) (x 3))\n\
+			(remove (path (x 2)) (x 4))\n\
+			(remove (path y) (y (x 5)))\n\
+			(add (path z) (z (x 4)))\n",
+		);
+		roundtrip(
+			"(x 1) (a (m 1)) (x 2) (b) (x 3)",
+			"(a) (b (m 1)) (x 1)",
+			"(remove (path (x 1)) (x 2))\n\
+			(move (path a m) (path b m))\n\
+			(remove (path (x 1)) (x 3))\n",
+		);
+		roundtrip("a (b)", "c (b)", "(replace (path) (a (b)) (c (b)))\n");
+		roundtrip(
+			"(\"a b\" (c))",
+			"(\"a b\")",
+			"(remove (path \"a b\" c) (c))\n",
+		);
+		roundtrip("(a)", "(a)", "");
+	}
+
+	#[test]
+	fn conflicts() {
+		let patch = doc("(remove (path a b) (b 1))\n(add (path a c) (c 2))");
+		let patch = Patch::parse(&patch).unwrap();
+		let mut d = doc("(a (b 1))");
+		patch.apply(&mut d).unwrap();
+		assert_eq!(d.to_string(), "(a (c 2))\n");
+
+		let mut d = doc("(a (b 2))");
+		let e = patch.apply(&mut d).unwrap_err();
+		assert_eq!((e.kind, e.op), (PatchErrorKind::Mismatch, 0));
+		assert_eq!(
+			e.to_string(),
+			"operation 0 at `a/b`: group differs from the patch"
+		);
+		assert_eq!(d.to_string(), "(a (b 2))\n");
+
+		let mut d = doc("(a (b 1) (c 3))");
+		let e = patch.apply(&mut d).unwrap_err();
+		assert_eq!((e.kind, e.op), (PatchErrorKind::Exists, 1));
+		assert_eq!(d.to_string(), "(a (b 1) (c 3))\n");
+
+		let mut d = doc("(x)");
+		let e = patch.apply(&mut d).unwrap_err();
+		assert_eq!((e.kind, e.path.as_str()), (PatchErrorKind::NotFound, "a/b"));
+
+		let patch = doc("(move (path a b) (path x b))");
+		let e = Patch::parse(&patch)
+			.unwrap()
+			.apply(&mut doc("(a (b))"))
+			.unwrap_err();
+		assert_eq!((e.kind, e.path.as_str()), (PatchErrorKind::NotFound, "x/b"));
+	}
+
+	#[test]
+	fn invalid() {
+		for s in [
+			"x",
+			"(add (path a) (b))",
+			"(add (path) (b))",
+			"(remove (path) (a))",
+			"(remove (path a) a)",
+			"(replace (path a) (a) b)",
+			"(move (path a) (path b))",
+			"(move (path (a x)) (path a))",
+			"(frobnicate)",
+		] {
+			let d = doc(s);
+			let e = Patch::parse(&d).unwrap_err();
+			assert_eq!((e.kind, e.op), (PatchErrorKind::Invalid, 0), "{}", s);
+			assert_eq!(e.span, Some(d.root.items[0].span()));
+		}
+	}
+}
@@ -0,0 +1,242 @@
+use crate::{Groups, GroupsIter, Iter, Span, Token};
+
+/// A sequence of head symbols identifying nested groups.
+///
+/// This is implemented for slices and arrays of strings, where every element is a head,
+/// and for `/`-separated strings such as `"pci-drivers/1af4/1000"`.
+/// Use a slice if a head contains a `/`.
+pub trait KeyPath {
+	/// The head at `depth`, or `None` if the path is shorter.
+	fn segment(&self, depth: usize) -> Option<&str>;
+}
+
+/// Empty segments are skipped and whitespace around segments is ignored.
+impl KeyPath for str {
+	fn segment(&self, depth: usize) -> Option<&str> {
+		self.split('/')
+			.map(str::trim)
+			.filter(|s| !s.is_empty())
+			.nth(depth)
+	}
+}
+
+impl<S: AsRef<str>> KeyPath for [S] {
+	fn segment(&self, depth: usize) -> Option<&str> {
+		self.get(depth).map(AsRef::as_ref)
+	}
+}
+
+impl<S: AsRef<str>, const N: usize> KeyPath for [S; N] {
+	fn segment(&self, depth: usize) -> Option<&str> {
+		self[..].segment(depth)
+	}
+}
+
+impl<P: KeyPath + ?Sized> KeyPath for &P {
+	fn segment(&self, depth: usize) -> Option<&str> {
+		(**self).segment(depth)
+	}
+}
+
+impl<'a> Groups<'a> {
+	/// The remaining items of the first group at `path`, i.e. the items after its head.
+	///
+	/// The search starts at the beginning of the document.
+	pub fn lookup<P: KeyPath>(&mut self, path: P) -> Option<GroupsIter<'a, '_>> {
+		self.lookup_all(path).find()
+	}
+
+	/// The remaining items of every group at `path`, in document order.
+	///
+	/// A group is only searched if its head and that of all groups around it match the path.
+	/// An empty path matches nothing.
+	///
+	/// The groups share the position in the document, so [`Lookup`] is not an [`Iterator`]:
+	/// each [`GroupsIter`] borrows it and must be dropped before the next one is requested.
+	///
+	/// ```
+	/// let mut cf = scf::parse2(b"(a (b 1)) (a (b 2))");
+	/// let mut all = cf.lookup_all("a/b");
+	/// while let Some(mut it) = all.next() {
+	///     println!("{}", it.next_str().unwrap());
+	/// }
+	/// ```
+	pub fn lookup_all<P: KeyPath>(&mut self, path: P) -> Lookup<'a, '_, P> {
+		if self.error.get().is_none() {
+			self.index.set(0);
+		}
+		Lookup {
+			groups: self,
+			path,
+			depth: 0,
+			matched: 0,
+		}
+	}
+}
+
+/// The groups at a [`KeyPath`].
+///
+/// See [`Groups::lookup_all`].
+#[derive(Debug)]
+pub struct Lookup<'a, 'b, P> {
+	groups: &'b Groups<'a>,
+	path: P,
+	/// The amount of groups around the current position.
+	depth: usize,
+	/// The amount of groups around the current position whose heads match the path.
+	matched: usize,
+}
+
+impl<'a, P: KeyPath> Lookup<'a, '_, P> {
+	/// The remaining items of the next group at the path.
+	#[allow(clippy::should_implement_trait)]
+	pub fn next(&mut self) -> Option<GroupsIter<'a, '_>> {
+		self.find()
+	}
+
+	/// Call `f` with the remaining items of every group at the path.
+	pub fn for_each(mut self, mut f: impl FnMut(GroupsIter<'a, '_>)) {
+		while let Some(it) = self.next() {
+			f(it);
+		}
+	}
+}
+
+impl<'a, 'b, P> Lookup<'a, 'b, P> {
+	/// Find the error in the document by iterating over all of it.
+	///
+	/// This way errors are the same as those of [`GroupsIter`].
+	fn fail(&mut self) -> Option<GroupsIter<'a, 'b>> {
+		let r = self.groups;
+		let mut cf = crate::parse2(r.data);
+		cf.permissive = r.permissive;
+		for _ in cf.iter() {}
+		r.error.set(cf.error());
+		None
+	}
+}
+
+impl<'a, 'b, P: KeyPath> Lookup<'a, 'b, P> {
+	fn find(&mut self) -> Option<GroupsIter<'a, 'b>> {
+		let r = self.groups;
+		if r.error.get().is_some() || self.path.segment(0).is_none() {
+			return None;
+		}
+		let mut it = Iter {
+			data: r.data,
+			index: r.index.get(),
+			permissive: r.permissive,
+		};
+		loop {
+			let (tk, span) = match it.next_spanned() {
+				None if self.depth == 0 => {
+					r.index.set(it.index);
+					return None;
+				}
+				Some(Ok(t)) => t,
+				_ => return self.fail(),
+			};
+			match tk {
+				Token::Begin => {
+					self.depth += 1;
+					if self.matched + 1 != self.depth {
+						continue;
+					}
+					let Some(segment) = self.path.segment(self.matched) else {
+						continue;
+					};
+					let mut peek = it.clone();
+					let Some(Ok((head, head_span))) = peek.next_spanned() else {
+						continue;
+					};
+					if head.into_str() != Some(segment) {
+						continue;
+					}
+					it = peek;
+					self.matched += 1;
+					if self.path.segment(self.matched).is_none() {
+						r.index.set(it.index);
+						// The group is consumed by the caller.
+						self.depth -= 1;
+						self.matched -= 1;
+						return Some(GroupsIter {
+							groups: r,
+							done: false,
+							nested: true,
+							span: Span {
+								start: span.start,
+								end: head_span.end,
+							},
+							start: None,
+						});
+					}
+				}
+				Token::End if self.depth == 0 => return self.fail(),
+				Token::End => {
+					self.matched = self.matched.min(self.depth - 1);
+					self.depth -= 1;
+				}
+				_ => {}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::ErrorKind;
+
+	const PCI: &[u8] = br#"(pci-drivers
+	(1af4 ; Red Hat
+		(1000 "drivers/pci/virtio/net")
+		(1001 "drivers/pci/virtio/blk"))
+	(8086 ; Intel
+		(1616 "drivers/pci/intel/hd_graphics"))
+	(1af4 ; Red Hat, again
+		(1000 "drivers/pci/virtio/net-legacy")))
+(1000 top)"#;
+
+	#[test]
+	fn lookup() {
+		let mut cf = crate::parse2(PCI);
+		let mut it = cf.lookup("pci-drivers/1af4/1001").unwrap();
+		assert_eq!(it.next_str(), Some("drivers/pci/virtio/blk"));
+		assert!(it.next().is_none());
+		drop(it);
+		let it = cf.lookup(["pci-drivers", "8086"]).unwrap();
+		assert_eq!(it.span().start, 100);
+		drop(it);
+		let mut it = cf.lookup(&["1000"][..]).unwrap();
+		assert_eq!(it.next_str(), Some("top"));
+		drop(it);
+		assert!(cf.lookup(" pci-drivers / ffff ").is_none());
+		assert!(cf.lookup("").is_none());
+
+		let mut paths = Vec::new();
+		let mut all = cf.lookup_all("pci-drivers/1af4/1000");
+		while let Some(mut it) = all.next() {
+			paths.push(it.next_str().unwrap());
+		}
+		assert_eq!(
+			paths,
+			["drivers/pci/virtio/net", "drivers/pci/virtio/net-legacy"]
+		);
+		let mut n = 0;
+		cf.lookup_all("pci-drivers/1af4").for_each(|_| n += 1);
+		assert_eq!(n, 2);
+		assert!(cf.into_error().is_none());
+	}
+
+	#[test]
+	fn errors() {
+		let mut cf = crate::parse2(b"(a (b 1)) (c (d");
+		assert!(cf.lookup("a/b").is_some());
+		assert!(cf.lookup("c/x").is_none());
+		let e = cf.into_error().unwrap();
+		assert_eq!((e.kind, e.offset), (ErrorKind::UnclosedGroup, 13));
+
+		let mut cf = crate::parse2(b"(a)) (b)");
+		assert!(cf.lookup("b").is_none());
+		assert_eq!(cf.error().unwrap().kind, ErrorKind::UnmatchedClose);
+	}
+}
@@ -0,0 +1,747 @@
+//! Serialize S documents with [`serde`].
+//!
+//! The output follows the same conventions as the [deserializer](crate::de),
+//! so deserializing it gives back the same value.
+//! Enum variants with data are always written as a group, e.g. `(Rgb 1 2 3)`.
+//! Strings are only quoted if they can't be written as a bare symbol.
+//!
+//! `Some` of a value without items, such as an empty sequence or `()`, is an error,
+//! since it would be written the same as `None`.
+
+use crate::{FormatOptions, WriteError, Writer};
+use alloc::string::{String, ToString};
+use core::fmt;
+use serde::ser::{self, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+	message: String,
+}
+
+impl Error {
+	pub fn message(&self) -> &str {
+		&self.message
+	}
+}
+
+impl From<WriteError> for Error {
+	fn from(e: WriteError) -> Self {
+		ser::Error::custom(e)
+	}
+}
+
+impl ser::Error for Error {
+	fn custom<T: fmt::Display>(msg: T) -> Self {
+		Self {
+			message: msg.to_string(),
+		}
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.message)
+	}
+}
+
+impl core::error::Error for Error {}
+
+/// Serialize `value` as a compact document.
+///
+/// Top-level items are put on separate lines.
+pub fn to_writer<W: fmt::Write, T: Serialize + ?Sized>(out: W, value: &T) -> Result<W, Error> {
+	let mut w = Writer::new(out);
+	value.serialize(Serializer::new(&mut w))?;
+	Ok(w.finish()?)
+}
+
+/// Serialize `value` as a compact document.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+	to_writer(String::new(), value)
+}
+
+/// Serialize `value` as a document formatted with [`format_with`](crate::format_with).
+pub fn to_writer_pretty<W: fmt::Write, T: Serialize + ?Sized>(
+	mut out: W,
+	value: &T,
+	options: &FormatOptions,
+) -> Result<W, Error> {
+	let s = to_string(value)?;
+	crate::format_with(s.as_bytes(), &mut out, options).map_err(<Error as ser::Error>::custom)?;
+	Ok(out)
+}
+
+/// Serialize `value` as a document formatted with the default options.
+pub fn to_string_pretty<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+	to_writer_pretty(String::new(), value, &FormatOptions::default())
+}
+
+/// A serializer that writes to a [`Writer`].
+pub struct Serializer<'w, W> {
+	w: &'w mut Writer<W>,
+	/// Whether the value is written as a sequence of items instead of a single item.
+	items: bool,
+	/// Whether the value is inside `Some` and must not be empty.
+	some: bool,
+}
+
+impl<'w, W: fmt::Write> Serializer<'w, W> {
+	/// Create a serializer that writes a value as a sequence of items,
+	/// i.e. a struct is written as `(field value)` groups without surrounding parentheses.
+	pub fn new(w: &'w mut Writer<W>) -> Self {
+		Self {
+			w,
+			items: true,
+			some: false,
+		}
+	}
+
+	fn item(w: &mut Writer<W>) -> Serializer<'_, W> {
+		Serializer {
+			w,
+			items: false,
+			some: false,
+		}
+	}
+
+	fn atom(self, s: &str) -> Result<(), Error> {
+		Ok(self.w.atom(s)?)
+	}
+
+	fn empty(self) -> Result<(), Error> {
+		if self.some {
+			return Err(some_empty());
+		}
+		if !self.items {
+			self.w.begin()?;
+			self.w.end()?;
+		}
+		Ok(())
+	}
+
+	fn compound(self) -> Result<Compound<'w, W>, Error> {
+		if !self.items {
+			self.w.begin()?;
+		}
+		Ok(Compound {
+			w: self.w,
+			close: !self.items,
+			some: self.some,
+		})
+	}
+
+	fn variant(self, variant: &str) -> Result<Compound<'w, W>, Error> {
+		self.w.begin()?;
+		self.w.atom(variant)?;
+		Ok(Compound {
+			w: self.w,
+			close: true,
+			some: false,
+		})
+	}
+}
+
+pub struct Compound<'w, W> {
+	w: &'w mut Writer<W>,
+	close: bool,
+	/// Whether the value is inside `Some` and no items were written yet.
+	some: bool,
+}
+
+impl<W: fmt::Write> Compound<'_, W> {
+	fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+		self.some = false;
+		value.serialize(Serializer::item(self.w))
+	}
+
+	fn field<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), Error> {
+		self.some = false;
+		self.w.begin()?;
+		self.w.atom(key)?;
+		value.serialize(Serializer::new(self.w))?;
+		Ok(self.w.end()?)
+	}
+
+	fn finish(self) -> Result<(), Error> {
+		if self.some {
+			return Err(some_empty());
+		}
+		if self.close {
+			self.w.end()?;
+		}
+		Ok(())
+	}
+}
+
+fn some_empty() -> Error {
+	ser::Error::custom("`Some` of an empty value can't be distinguished from `None`")
+}
+
+macro_rules! display {
+	($($f:ident $t:ty)*) => {
+		$(
+			fn $f(self, v: $t) -> Result<(), Error> {
+				self.atom(&v.to_string())
+			}
+		)*
+	};
+}
+
+impl<'w, W: fmt::Write> ser::Serializer for Serializer<'w, W> {
+	type Ok = ();
+	type Error = Error;
+	type SerializeSeq = Compound<'w, W>;
+	type SerializeTuple = Compound<'w, W>;
+	type SerializeTupleStruct = Compound<'w, W>;
+	type SerializeTupleVariant = Compound<'w, W>;
+	type SerializeMap = Compound<'w, W>;
+	type SerializeStruct = Compound<'w, W>;
+	type SerializeStructVariant = Compound<'w, W>;
+
+	display! {
+		serialize_bool bool
+		serialize_i8 i8
+		serialize_i16 i16
+		serialize_i32 i32
+		serialize_i64 i64
+		serialize_i128 i128
+		serialize_u8 u8
+		serialize_u16 u16
+		serialize_u32 u32
+		serialize_u64 u64
+		serialize_u128 u128
+		serialize_f32 f32
+		serialize_f64 f64
+	}
+
+	fn serialize_char(self, v: char) -> Result<(), Error> {
+		self.atom(v.encode_utf8(&mut [0; 4]))
+	}
+
+	fn serialize_str(self, v: &str) -> Result<(), Error> {
+		self.atom(v)
+	}
+
+	fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+		let s = core::str::from_utf8(v)
+			.map_err(|_| <Error as ser::Error>::custom("bytes are not valid UTF-8"))?;
+		self.atom(s)
+	}
+
+	fn serialize_none(self) -> Result<(), Error> {
+		self.empty()
+	}
+
+	fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+		value.serialize(Serializer { some: true, ..self })
+	}
+
+	fn serialize_unit(self) -> Result<(), Error> {
+		self.empty()
+	}
+
+	fn serialize_unit_struct(self, _: &'static str) -> Result<(), Error> {
+		self.empty()
+	}
+
+	fn serialize_unit_variant(
+		self,
+		_: &'static str,
+		_: u32,
+		variant: &'static str,
+	) -> Result<(), Error> {
+		self.atom(variant)
+	}
+
+	fn serialize_newtype_struct<T: Serialize + ?Sized>(
+		self,
+		_: &'static str,
+		value: &T,
+	) -> Result<(), Error> {
+		value.serialize(self)
+	}
+
+	fn serialize_newtype_variant<T: Serialize + ?Sized>(
+		self,
+		_: &'static str,
+		_: u32,
+		variant: &'static str,
+		value: &T,
+	) -> Result<(), Error> {
+		let c = self.variant(variant)?;
+		value.serialize(Serializer::new(c.w))?;
+		Ok(c.w.end()?)
+	}
+
+	fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+		self.compound()
+	}
+
+	fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, Error> {
+		self.compound()
+	}
+
+	fn serialize_tuple_struct(
+		self,
+		_: &'static str,
+		_: usize,
+	) -> Result<Self::SerializeTupleStruct, Error> {
+		self.compound()
+	}
+
+	fn serialize_tuple_variant(
+		self,
+		_: &'static str,
+		_: u32,
+		variant: &'static str,
+		_: usize,
+	) -> Result<Self::SerializeTupleVariant, Error> {
+		self.variant(variant)
+	}
+
+	fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Error> {
+		self.compound()
+	}
+
+	fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeStruct, Error> {
+		self.compound()
+	}
+
+	fn serialize_struct_variant(
+		self,
+		_: &'static str,
+		_: u32,
+		variant: &'static str,
+		_: usize,
+	) -> Result<Self::SerializeStructVariant, Error> {
+		self.variant(variant)
+	}
+}
+
+macro_rules! elements {
+	($($t:ident $f:ident)*) => {
+		$(
+			impl<W: fmt::Write> ser::$t for Compound<'_, W> {
+				type Ok = ();
+				type Error = Error;
+
+				fn $f<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+					self.element(value)
+				}
+
+				fn end(self) -> Result<(), Error> {
+					self.finish()
+				}
+			}
+		)*
+	};
+}
+
+elements! {
+	SerializeSeq serialize_element
+	SerializeTuple serialize_element
+	SerializeTupleStruct serialize_field
+	SerializeTupleVariant serialize_field
+}
+
+impl<W: fmt::Write> ser::SerializeMap for Compound<'_, W> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+		self.some = false;
+		self.w.begin()?;
+		key.serialize(KeySerializer { w: self.w })
+	}
+
+	fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+		value.serialize(Serializer::new(self.w))?;
+		Ok(self.w.end()?)
+	}
+
+	fn end(self) -> Result<(), Error> {
+		self.finish()
+	}
+}
+
+impl<W: fmt::Write> ser::SerializeStruct for Compound<'_, W> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T: Serialize + ?Sized>(
+		&mut self,
+		key: &'static str,
+		value: &T,
+	) -> Result<(), Error> {
+		self.field(key, value)
+	}
+
+	fn end(self) -> Result<(), Error> {
+		self.finish()
+	}
+}
+
+impl<W: fmt::Write> ser::SerializeStructVariant for Compound<'_, W> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T: Serialize + ?Sized>(
+		&mut self,
+		key: &'static str,
+		value: &T,
+	) -> Result<(), Error> {
+		self.field(key, value)
+	}
+
+	fn end(self) -> Result<(), Error> {
+		self.finish()
+	}
+}
+
+/// Serializes map keys, which must be a single symbol or string.
+struct KeySerializer<'w, W> {
+	w: &'w mut Writer<W>,
+}
+
+fn key_error() -> Error {
+	ser::Error::custom("map keys must be a symbol or string")
+}
+
+impl<W: fmt::Write> ser::Serializer for KeySerializer<'_, W> {
+	type Ok = ();
+	type Error = Error;
+	type SerializeSeq = ser::Impossible<(), Error>;
+	type SerializeTuple = ser::Impossible<(), Error>;
+	type SerializeTupleStruct = ser::Impossible<(), Error>;
+	type SerializeTupleVariant = ser::Impossible<(), Error>;
+	type SerializeMap = ser::Impossible<(), Error>;
+	type SerializeStruct = ser::Impossible<(), Error>;
+	type SerializeStructVariant = ser::Impossible<(), Error>;
+
+	fn serialize_bool(self, v: bool) -> Result<(), Error> {
+		Serializer::item(self.w).serialize_bool(v)
+	}
+
+	fn serialize_i8(self, v: i8) -> Result<(), Error> {
+		Serializer::item(self.w).serialize_i8(v)
+	}
+
+	fn serialize_i16(self, v: i16) -> Result<(), Error> {
+		Serializer::item(self.w).serialize_i16(v)
+	}
+
+	fn serialize_i32(self, v: i32) -> Result<(), Error> {
+		Serializer::item(self.w).serialize_i32(v)
+	}
+
+	fn serialize_i64(self, v: i64) -> Result<(), Error> {
+		Serializer::item(self.w).serialize_i64(v)
+	}
+
+	fn serialize_u8(self, v: u8) -> Result<(), Error> {
+		Serializer::item(self.w).serialize_u8(v)
+	}
+
+	fn serialize_u16(self, v: u16) -> Result<(), Error> {
+		Serializer::item(self.w).serialize_u16(v)
+	}
+
+	fn serialize_u32(self, v: u32) -> Result<(), Error> {
+		Serializer::item(self.w).serialize_u32(v)
+	}
+
+	fn serialize_u64(self, v: u64) -> Result<(), Error> {
+		Serializer::item(self.w).serialize_u64(v)
+	}
+
+	fn serialize_f32(self, v: f32) -> Result<(), Error> {
+		Serializer::item(self.w).serialize_f32(v)
+	}
+
+	fn serialize_f64(self, v: f64) -> Result<(), Error> {
+		Serializer::item(self.w).serialize_f64(v)
+	}
+
+	fn serialize_char(self, v: char) -> Result<(), Error> {
+		Serializer::item(self.w).serialize_char(v)
+	}
+
+	fn serialize_str(self, v: &str) -> Result<(), Error> {
+		Serializer::item(self.w).serialize_str(v)
+	}
+
+	fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+		Serializer::item(self.w).serialize_bytes(v)
+	}
+
+	fn serialize_none(self) -> Result<(), Error> {
+		Err(key_error())
+	}
+
+	fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+		value.serialize(self)
+	}
+
+	fn serialize_unit(self) -> Result<(), Error> {
+		Err(key_error())
+	}
+
+	fn serialize_unit_struct(self, _: &'static str) -> Result<(), Error> {
+		Err(key_error())
+	}
+
+	fn serialize_unit_variant(
+		self,
+		_: &'static str,
+		_: u32,
+		variant: &'static str,
+	) -> Result<(), Error> {
+		self.serialize_str(variant)
+	}
+
+	fn serialize_newtype_struct<T: Serialize + ?Sized>(
+		self,
+		_: &'static str,
+		value: &T,
+	) -> Result<(), Error> {
+		value.serialize(self)
+	}
+
+	fn serialize_newtype_variant<T: Serialize + ?Sized>(
+		self,
+		_: &'static str,
+		_: u32,
+		_: &'static str,
+		_: &T,
+	) -> Result<(), Error> {
+		Err(key_error())
+	}
+
+	fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+		Err(key_error())
+	}
+
+	fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, Error> {
+		Err(key_error())
+	}
+
+	fn serialize_tuple_struct(
+		self,
+		_: &'static str,
+		_: usize,
+	) -> Result<Self::SerializeTupleStruct, Error> {
+		Err(key_error())
+	}
+
+	fn serialize_tuple_variant(
+		self,
+		_: &'static str,
+		_: u32,
+		_: &'static str,
+		_: usize,
+	) -> Result<Self::SerializeTupleVariant, Error> {
+		Err(key_error())
+	}
+
+	fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Error> {
+		Err(key_error())
+	}
+
+	fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeStruct, Error> {
+		Err(key_error())
+	}
+
+	fn serialize_struct_variant(
+		self,
+		_: &'static str,
+		_: u32,
+		_: &'static str,
+		_: usize,
+	) -> Result<Self::SerializeStructVariant, Error> {
+		Err(key_error())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use serde::{Deserialize, Serialize};
+	use std::collections::BTreeMap;
+
+	#[derive(Serialize, Deserialize, Debug, PartialEq)]
+	enum Color {
+		Red,
+		Gray(u8),
+		Rgb(u8, u8, u8),
+		Named { name: String },
+	}
+
+	#[derive(Serialize, Deserialize, Debug, PartialEq)]
+	struct Config {
+		name: String,
+		number: String,
+		enabled: bool,
+		ratio: f64,
+		offset: i32,
+		ports: Vec<u16>,
+		matrix: Vec<Vec<u8>>,
+		pairs: Vec<(u8, String)>,
+		colors: Vec<Color>,
+		color: Color,
+		gray: Color,
+		nested: Nested,
+		nested_list: Vec<Nested>,
+		maybe: Option<u32>,
+		empty: Option<u32>,
+		map: BTreeMap<u32, String>,
+		unit: (),
+	}
+
+	#[derive(Serialize, Deserialize, Debug, PartialEq)]
+	struct Nested {
+		a: u8,
+		b: Option<Box<Nested>>,
+	}
+
+	#[test]
+	fn roundtrip() {
+		let cfg = Config {
+			name: "hd graphics".into(),
+			number: "1000".into(),
+			enabled: true,
+			ratio: -0.25,
+			offset: -12,
+			ports: vec![80, 443],
+			matrix: vec![vec![1, 2], vec![], vec![3]],
+			pairs: vec![(1, "one".into()), (2, "\"two\"\n".into())],
+			colors: vec![
+				Color::Red,
+				Color::Gray(3),
+				Color::Rgb(1, 2, 3),
+				Color::Named {
+					name: "light blue".into(),
+				},
+			],
+			color: Color::Red,
+			gray: Color::Gray(7),
+			nested: Nested {
+				a: 1,
+				b: Some(Box::new(Nested { a: 2, b: None })),
+			},
+			nested_list: vec![Nested { a: 3, b: None }],
+			maybe: Some(5),
+			empty: None,
+			map: [(1, "x".into()), (2, "".into())].into_iter().collect(),
+			unit: (),
+		};
+		let s = to_string(&cfg).unwrap();
+		assert_eq!(
+			s,
+			r#"(name "hd graphics")
+(number 1000)
+(enabled true)
+(ratio -0.25)
+(offset -12)
+(ports 80 443)
+(matrix (1 2) () (3))
+(pairs (1 one) (2 "\"two\"\n"))
+(colors Red (Gray 3) (Rgb 1 2 3) (Named (name "light blue")))
+(color Red)
+(gray (Gray 7))
+(nested (a 1) (b (a 2) (b)))
+(nested_list ((a 3) (b)))
+(maybe 5)
+(empty)
+(map (1 x) (2 ""))
+(unit)"#
+		);
+		assert_eq!(crate::from_str::<Config>(&s).unwrap(), cfg);
+
+		let s = to_string_pretty(&cfg).unwrap();
+		assert!(s.contains("\n(nested (a 1) (b (a 2) (b)))\n"));
+		assert_eq!(crate::from_str::<Config>(&s).unwrap(), cfg);
+	}
+
+	#[test]
+	fn pci() {
+		#[derive(Serialize)]
+		struct Pci<'a> {
+			#[serde(rename = "pci-drivers")]
+			drivers: BTreeMap<&'a str, BTreeMap<&'a str, &'a str>>,
+		}
+		let mut drivers = BTreeMap::new();
+		drivers.insert(
+			"1af4",
+			[
+				("1000", "drivers/pci/virtio/net"),
+				("1001", "drivers/pci/virtio/blk"),
+				("1040", "drivers/pci/virtio/gpu"),
+			]
+			.into_iter()
+			.collect(),
+		);
+		drivers.insert(
+			"8086",
+			[("1616", "drivers/pci/intel/hd_graphics")]
+				.into_iter()
+				.collect(),
+		);
+		let options = FormatOptions {
+			width: 50,
+			..Default::default()
+		};
+		let s = to_writer_pretty(String::new(), &Pci { drivers }, &options).unwrap();
+		assert_eq!(
+			s,
+			"(pci-drivers
+	(1af4
+		(1000 drivers/pci/virtio/net)
+		(1001 drivers/pci/virtio/blk)
+		(1040 drivers/pci/virtio/gpu))
+	(8086 (1616 drivers/pci/intel/hd_graphics)))
+"
+		);
+	}
+
+	#[test]
+	fn errors() {
+		let mut m = BTreeMap::new();
+		m.insert(vec![1], 2);
+		assert_eq!(
+			to_string(&m).unwrap_err().message(),
+			"map keys must be a symbol or string"
+		);
+	}
+
+	#[test]
+	fn some_empty() {
+		#[derive(Serialize, Deserialize, Debug, PartialEq)]
+		struct S {
+			tags: Option<Vec<u32>>,
+			unit: Option<()>,
+			list: Vec<Option<Vec<u32>>>,
+		}
+		let s = |tags, unit, list| {
+			let v = S { tags, unit, list };
+			let s = to_string(&v)?;
+			assert_eq!(crate::from_str::<S>(&s).unwrap(), v);
+			Ok::<_, Error>(s)
+		};
+		assert_eq!(
+			s(Some(vec![]), None, vec![]).unwrap_err().message(),
+			"`Some` of an empty value can't be distinguished from `None`"
+		);
+		assert!(s(None, Some(()), vec![]).is_err());
+		assert!(s(None, None, vec![Some(vec![])]).is_err());
+		assert_eq!(
+			s(Some(vec![1]), None, vec![None, Some(vec![2])]).unwrap(),
+			"(tags 1)\n(unit)\n(list () (2))"
+		);
+
+		let v: Option<Option<u32>> = Some(None);
+		assert!(to_string(&v).is_err());
+		let v: Option<Vec<Vec<u32>>> = Some(vec![vec![]]);
+		assert_eq!(to_string(&v).unwrap(), "()");
+		assert_eq!(crate::from_str::<Option<Vec<Vec<u32>>>>("()").unwrap(), v);
+	}
+}
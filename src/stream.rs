@@ -0,0 +1,428 @@
+//! Tokenize a document while reading it in chunks into a fixed buffer.
+//!
+//! ```
+//! use scf::{stream::Stream, Token};
+//!
+//! let mut buf = [0; 16];
+//! let mut s = Stream::new(&b"(pci-drivers ; list\n (1af4 \"drivers/pci/virtio/net\"))"[..], &mut buf);
+//! assert_eq!(s.next_token().unwrap().unwrap().0, Token::Begin);
+//! assert_eq!(s.next_token().unwrap().unwrap().0, Token::Str("pci-drivers"));
+//! ```
+
+use crate::{advance, Error, ErrorKind, Iter, LineCol, Span, Token};
+use core::{convert::Infallible, fmt};
+
+/// A source of bytes for a [`Stream`].
+pub trait Read {
+	type Error;
+
+	/// Read bytes into `buf` and return how many were read.
+	/// Returning 0 means the end of the data was reached.
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error>;
+}
+
+impl Read for &[u8] {
+	type Error = Infallible;
+
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+		let n = buf.len().min(self.len());
+		buf[..n].copy_from_slice(&self[..n]);
+		*self = &self[n..];
+		Ok(n)
+	}
+}
+
+/// Adapts a [`std::io::Read`].
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct IoRead<R>(pub R);
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read> Read for IoRead<R> {
+	type Error = std::io::Error;
+
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+		loop {
+			match self.0.read(buf) {
+				Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+				r => return r,
+			}
+		}
+	}
+}
+
+/// Adapts an [`embedded_io::Read`].
+#[cfg(feature = "embedded-io")]
+#[derive(Debug)]
+pub struct EmbeddedRead<R>(pub R);
+
+#[cfg(feature = "embedded-io")]
+impl<R: embedded_io::Read> Read for EmbeddedRead<R> {
+	type Error = R::Error;
+
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+		self.0.read(buf)
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StreamError<E> {
+	/// The document is malformed.
+	Syntax(Error),
+	/// The reader failed.
+	Io(E),
+}
+
+impl<E> From<Error> for StreamError<E> {
+	fn from(e: Error) -> Self {
+		Self::Syntax(e)
+	}
+}
+
+impl<E: fmt::Display> fmt::Display for StreamError<E> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Syntax(e) => e.fmt(f),
+			Self::Io(e) => e.fmt(f),
+		}
+	}
+}
+
+impl<E: core::error::Error + 'static> core::error::Error for StreamError<E> {
+	fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+		match self {
+			Self::Syntax(e) => Some(e),
+			Self::Io(e) => Some(e),
+		}
+	}
+}
+
+/// A tokenizer that reads from a [`Read`] into a fixed buffer.
+///
+/// It produces the same tokens and errors as [`Iter`].
+/// Spans and error offsets are relative to the start of the stream.
+///
+/// Comments and whitespace may be of any length,
+/// but every symbol or string must fit in the buffer.
+#[derive(Debug)]
+pub struct Stream<'b, R> {
+	reader: R,
+	buf: &'b mut [u8],
+	/// The first byte in `buf` that hasn't been consumed yet.
+	start: usize,
+	/// The end of the data in `buf`.
+	end: usize,
+	/// The offset in the stream of `buf[0]`.
+	offset: usize,
+	/// The position of `buf[start]`.
+	line_col: LineCol,
+	max_atom: usize,
+	permissive: bool,
+	eof: bool,
+	done: bool,
+}
+
+impl<'b, R: Read> Stream<'b, R> {
+	pub fn new(reader: R, buf: &'b mut [u8]) -> Self {
+		Self {
+			reader,
+			buf,
+			start: 0,
+			end: 0,
+			offset: 0,
+			line_col: LineCol { line: 1, column: 1 },
+			max_atom: usize::MAX,
+			permissive: false,
+			eof: false,
+			done: false,
+		}
+	}
+
+	/// See [`Iter::permissive`].
+	pub fn permissive(mut self) -> Self {
+		self.permissive = true;
+		self
+	}
+
+	/// Limit the length of symbols and strings in bytes, including quotes.
+	///
+	/// Longer atoms result in [`ErrorKind::AtomTooLong`].
+	/// Atoms are always limited to the size of the buffer.
+	pub fn max_atom(mut self, len: usize) -> Self {
+		self.max_atom = len;
+		self
+	}
+
+	/// Return the underlying reader.
+	pub fn into_inner(self) -> R {
+		self.reader
+	}
+
+	/// Read the next token and its span.
+	///
+	/// After an error, this returns `None`.
+	#[allow(clippy::type_complexity)]
+	pub fn next_token(&mut self) -> Option<Result<(Token<'_>, Span), StreamError<R::Error>>> {
+		if self.done {
+			return None;
+		}
+		let len = match self.scan() {
+			Ok(Some(len)) => len,
+			Ok(None) => {
+				self.done = true;
+				return None;
+			}
+			Err(e) => {
+				self.done = true;
+				return Some(Err(e));
+			}
+		};
+		let (start, base, line_col) = (self.start, self.offset + self.start, self.line_col);
+		self.start += len;
+		let data = &self.buf[start..start + len];
+		self.line_col = advance(line_col, data);
+		let mut it = Iter::new(data);
+		it.permissive = self.permissive;
+		let r = match it.next_spanned() {
+			Some(Ok((tk, span))) => Ok((
+				tk,
+				Span {
+					start: base + span.start,
+					end: base + span.end,
+				},
+			)),
+			Some(Err(e)) => {
+				self.done = true;
+				Err(StreamError::Syntax(Error {
+					kind: e.kind,
+					offset: base + e.offset,
+					line_col: advance(line_col, &data[..e.offset]),
+				}))
+			}
+			None => unreachable!("scan found a token"),
+		};
+		Some(r)
+	}
+
+	fn error(&self, kind: ErrorKind) -> StreamError<R::Error> {
+		StreamError::Syntax(Error {
+			kind,
+			offset: self.offset + self.start,
+			line_col: self.line_col,
+		})
+	}
+
+	/// Read more data, moving unconsumed data to the start of the buffer.
+	///
+	/// Returns `false` at the end of the data or if the buffer is full.
+	fn fill(&mut self) -> Result<bool, StreamError<R::Error>> {
+		if self.eof {
+			return Ok(false);
+		}
+		if self.start > 0 {
+			self.buf.copy_within(self.start..self.end, 0);
+			self.offset += self.start;
+			self.end -= self.start;
+			self.start = 0;
+		}
+		if self.end == self.buf.len() {
+			return Ok(false);
+		}
+		let n = self
+			.reader
+			.read(&mut self.buf[self.end..])
+			.map_err(StreamError::Io)?;
+		self.end += n;
+		self.eof = n == 0;
+		Ok(n > 0)
+	}
+
+	fn consume(&mut self, len: usize) {
+		self.line_col = advance(self.line_col, &self.buf[self.start..self.start + len]);
+		self.start += len;
+	}
+
+	/// Skip whitespace and comments, then find the length of the next token.
+	fn scan(&mut self) -> Result<Option<usize>, StreamError<R::Error>> {
+		let mut comment = false;
+		loop {
+			if self.start == self.end && !self.fill()? {
+				return Ok(None);
+			}
+			let c = self.buf[self.start];
+			if comment || c == b';' || c.is_ascii_whitespace() {
+				comment = c != b'\n' && (comment || c == b';');
+				self.consume(1);
+				continue;
+			}
+			break;
+		}
+		let quote = match self.buf[self.start] {
+			b'(' | b')' => return Ok(Some(1)),
+			q @ (b'"' | b'\'') => Some(q),
+			_ => None,
+		};
+		let (mut len, mut escape) = (1, false);
+		loop {
+			if len > self.max_atom {
+				return Err(self.error(ErrorKind::AtomTooLong));
+			}
+			if self.start + len == self.end && !self.fill()? {
+				if !self.eof {
+					return Err(self.error(ErrorKind::AtomTooLong));
+				}
+				// Let `Iter` report unterminated quotes.
+				return Ok(Some(len));
+			}
+			let c = self.buf[self.start + len];
+			len += 1;
+			match quote {
+				Some(_) if escape => escape = false,
+				Some(_) if c == b'\\' => escape = true,
+				Some(q) if c == q && len > self.max_atom => {
+					return Err(self.error(ErrorKind::AtomTooLong));
+				}
+				Some(q) if c == q => return Ok(Some(len)),
+				None if c == b'(' || c == b')' || c.is_ascii_whitespace() => {
+					return Ok(Some(len - 1));
+				}
+				_ => {}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	/// Returns at most `n` bytes per read.
+	struct Chunks<'a>(&'a [u8], usize);
+
+	impl Read for Chunks<'_> {
+		type Error = Infallible;
+
+		fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+			let n = buf.len().min(self.1);
+			(&mut self.0).read(&mut buf[..n])
+		}
+	}
+
+	const INPUTS: &[&[u8]] = &[
+		br#"(pci-drivers
+	(1af4 ; Red Hat
+		(1000 "drivers/pci/virtio/net")
+		(1001 "drivers/pci/virtio/blk")
+		(1040 "drivers/pci/virtio/gpu"))
+	(8086 ; Intel, with a comment longer than any of the buffers used in this test
+		(1616 'drivers/pci/intel/hd graphics')))"#,
+		b"a\\b",
+		b"(a) \"\\\"x\\u{1f600}\" ; end",
+		b"\n\n  \"unterminated",
+		b"\"\\q\"",
+		b"abc\xff",
+		"çé 😀 'ü\n\\n'".as_bytes(),
+		b"",
+		b";",
+		b"x",
+	];
+
+	fn check(data: &[u8], buf_len: usize, chunk: usize, permissive: bool) {
+		let mut buf = [0; 64];
+		let mut s = Stream::new(Chunks(data, chunk), &mut buf[..buf_len]);
+		let mut it = Iter::new(data);
+		if permissive {
+			s = s.permissive();
+			it = it.permissive();
+		}
+		loop {
+			let (a, b) = (s.next_token(), it.next_spanned());
+			let a = a.map(|r| {
+				r.map_err(|e| match e {
+					StreamError::Syntax(e) => e,
+					StreamError::Io(e) => match e {},
+				})
+			});
+			assert_eq!(a, b, "{:?} {} {}", data, buf_len, chunk);
+			if a.is_none_or(|r| r.is_err()) {
+				break;
+			}
+		}
+		assert!(s.next_token().is_none());
+	}
+
+	#[test]
+	fn same_as_iter() {
+		for data in INPUTS {
+			for buf_len in [32, 64] {
+				for chunk in [1, 2, 3, 7, 64] {
+					check(data, buf_len, chunk, false);
+					check(data, buf_len, chunk, true);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn too_long() {
+		let data = b"(short\n  01234567890123456789)";
+		let mut buf = [0; 16];
+		let mut s = Stream::new(&data[..], &mut buf);
+		for _ in 0..2 {
+			s.next_token().unwrap().unwrap();
+		}
+		let e = s.next_token().unwrap().unwrap_err();
+		let StreamError::Syntax(e) = e;
+		assert_eq!(e.kind, ErrorKind::AtomTooLong);
+		assert_eq!((e.offset, e.line_col), (9, LineCol { line: 2, column: 3 }));
+		assert!(s.next_token().is_none());
+
+		let mut buf = [0; 64];
+		let mut s = Stream::new(&b"'abcd' abcdefg"[..], &mut buf).max_atom(6);
+		assert_eq!(
+			s.next_token().unwrap().unwrap().0,
+			Token::Quoted("abcd", crate::Quote::Single)
+		);
+		let e = s.next_token().unwrap().unwrap_err();
+		assert!(
+			matches!(e, StreamError::Syntax(e) if e.kind == ErrorKind::AtomTooLong && e.offset == 7)
+		);
+	}
+
+	#[test]
+	fn io_error() {
+		struct Fail<'a>(&'a [u8]);
+
+		impl Read for Fail<'_> {
+			type Error = &'static str;
+
+			fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+				match self.0.read(buf) {
+					Ok(0) => Err("broken pipe"),
+					r => Ok(r.unwrap()),
+				}
+			}
+		}
+
+		let mut buf = [0; 4];
+		let mut s = Stream::new(Fail(b"(ab cd"), &mut buf);
+		assert_eq!(s.next_token().unwrap().unwrap().0, Token::Begin);
+		assert_eq!(s.next_token().unwrap().unwrap().0, Token::Str("ab"));
+		assert_eq!(s.next_token().unwrap(), Err(StreamError::Io("broken pipe")));
+		assert!(s.next_token().is_none());
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn io() {
+		let data = std::io::Cursor::new(&b"(a b)"[..]);
+		let mut buf = [0; 8];
+		let mut s = Stream::new(IoRead(data), &mut buf);
+		let mut n = 0;
+		while let Some(r) = s.next_token() {
+			r.unwrap();
+			n += 1;
+		}
+		assert_eq!(n, 4);
+	}
+}
@@ -0,0 +1,245 @@
+//! Merge two documents that were changed independently from a common base.
+//!
+//! Groups are matched by head as in [`diff`](crate::diff). For each group:
+//!
+//! - If only one side changed it, that side is used.
+//!   Both sides making the same change is not a conflict.
+//! - If both sides changed it, the groups inside it are merged recursively
+//!   and the other items, such as symbols and strings, must be equal.
+//! - If one side removed it and the other changed it, that is a conflict.
+//! - Groups added by either side are kept, and merged if both added a group with the same head.
+//!
+//! Conflicts are marked with a `(!conflict (base ...) (ours ...) (theirs ...))` group
+//! in place of the conflicting items, so the result is still a valid document.
+//! Each side of the conflict contains the items of that version, which is empty
+//! if the group doesn't exist in it.
+//!
+//! ```
+//! use scf::{three_way, Document};
+//!
+//! let base = Document::parse(b"(console ttyS0) (modules a)").unwrap();
+//! let ours = Document::parse(b"(console tty0) (modules a b)").unwrap();
+//! let theirs = Document::parse(b"(console ttyS1) (modules a) (debug yes)").unwrap();
+//! let merged = three_way::merge(&base, &ours, &theirs);
+//! assert_eq!(merged.conflicts, ["console"]);
+//! assert_eq!(
+//!     merged.document.to_string(),
+//!     "(console (!conflict (base ttyS0) (ours tty0) (theirs ttyS1)))\n(modules a b)\n(debug yes)\n",
+//! );
+//! ```
+
+use crate::{diff::Step, Document, List, Value};
+use alloc::{
+	string::{String, ToString},
+	vec,
+	vec::Vec,
+};
+
+/// The head of groups marking conflicts.
+pub const CONFLICT: &str = "!conflict";
+
+/// The result of [`merge`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Merged<'a> {
+	pub document: Document<'a>,
+	/// The paths of the groups with conflicts, with steps separated by `/`
+	/// and written as in [`diff::Change`](crate::diff::Change).
+	/// Conflicts at the top level have an empty path.
+	pub conflicts: Vec<String>,
+}
+
+/// Merge the changes from `base` to `ours` and from `base` to `theirs`.
+///
+/// Items are in the order of `ours`, followed by items only in `base` or `theirs`.
+pub fn merge<'a>(base: &List<'a>, ours: &List<'a>, theirs: &List<'a>) -> Merged<'a> {
+	let mut m = Merger {
+		path: Vec::new(),
+		conflicts: Vec::new(),
+	};
+	let items = m.items([base, ours, theirs], 0);
+	Merged {
+		document: Document {
+			root: List {
+				items,
+				..ours.clone()
+			},
+		},
+		conflicts: m.conflicts,
+	}
+}
+
+struct Merger {
+	/// The steps to the current group.
+	path: Vec<String>,
+	conflicts: Vec<String>,
+}
+
+impl Merger {
+	/// A conflict between the items of `[base, ours, theirs]`.
+	fn conflict<'a>(&mut self, sides: [Vec<Value<'a>>; 3]) -> Value<'a> {
+		let path = self.path.join("/");
+		if !self.conflicts.contains(&path) {
+			self.conflicts.push(path);
+		}
+		let [base, ours, theirs] = sides;
+		let side = |name: &'static str, items: Vec<Value<'a>>| {
+			let mut l = vec![Value::from(name)];
+			l.extend(items);
+			Value::from(l)
+		};
+		Value::from(vec![
+			Value::from(CONFLICT),
+			side("base", base),
+			side("ours", ours),
+			side("theirs", theirs),
+		])
+	}
+
+	/// Merge the items of `[base, ours, theirs]` after the first `start`.
+	fn items<'a>(&mut self, lists: [&List<'a>; 3], start: usize) -> Vec<Value<'a>> {
+		let items = lists.map(|l| l.items.get(start..).unwrap_or_default());
+		let [vb, vo, vt] = items.map(|items| {
+			let v = items.iter().filter(|v| v.head().is_none());
+			v.cloned().collect::<Vec<_>>()
+		});
+		let values = if vo == vt || vt == vb {
+			vo
+		} else if vo == vb {
+			vt
+		} else {
+			vec![self.conflict([vb, vo, vt])]
+		};
+
+		// Values are placed where the first value of `ours` is.
+		let mut values = Some(values);
+		let mut out = Vec::new();
+		let mut done = Vec::new();
+		for (i, v) in items[1].iter().enumerate() {
+			match step(items[1], i, v) {
+				Some(s) => {
+					done.push(s);
+					self.group(s, items, &mut out);
+				}
+				None => out.extend(values.take().into_iter().flatten()),
+			}
+		}
+		out.extend(values.into_iter().flatten());
+		for side in [items[2], items[0]] {
+			for (i, v) in side.iter().enumerate() {
+				match step(side, i, v) {
+					Some(s) if !done.contains(&s) => {
+						done.push(s);
+						self.group(s, items, &mut out);
+					}
+					_ => {}
+				}
+			}
+		}
+		out
+	}
+
+	/// Merge the groups at `step` in `[base, ours, theirs]` and add the result to `out`.
+	fn group<'a>(&mut self, step: Step<'_>, items: [&[Value<'a>]; 3], out: &mut Vec<Value<'a>>) {
+		let [b, o, t] = items.map(|items| find(items, step));
+		self.path.push(step.to_string());
+		match (b, o, t) {
+			(_, Some(o), Some(t)) if o == t => out.push(o.clone().into()),
+			(Some(b), Some(o), Some(t)) if b == t => out.push(o.clone().into()),
+			(Some(b), Some(o), Some(t)) if b == o => out.push(t.clone().into()),
+			(b, Some(o), Some(t)) => {
+				// Both sides changed the group or added one with this head.
+				let empty = List::new(o.items[..1].to_vec());
+				let mut items = o.items[..1].to_vec();
+				items.extend(self.items([b.unwrap_or(&empty), o, t], 1));
+				out.push(List { items, ..o.clone() }.into());
+			}
+			(Some(b), Some(g), None) | (Some(b), None, Some(g)) if b == g => {}
+			(Some(b), o, t) => {
+				let side =
+					|g: Option<&List<'a>>| g.map(|g| vec![g.clone().into()]).unwrap_or_default();
+				out.push(self.conflict([side(Some(b)), side(o), side(t)]));
+			}
+			(None, Some(g), None) | (None, None, Some(g)) => out.push(g.clone().into()),
+			(None, None, None) => {}
+		}
+		self.path.pop();
+	}
+}
+
+/// The step of `items[i]` if it is a group with a head.
+fn step<'d>(items: &'d [Value<'_>], i: usize, v: &'d Value<'_>) -> Option<Step<'d>> {
+	let head = v.head()?;
+	let index = items[..i].iter().filter(|v| v.head() == Some(head)).count();
+	Some(Step { head, index })
+}
+
+/// The group at `step` in `items`.
+fn find<'d, 'a>(items: &'d [Value<'a>], step: Step<'_>) -> Option<&'d List<'a>> {
+	let mut groups = items.iter().filter(|v| v.head() == Some(step.head));
+	groups.nth(step.index).and_then(Value::as_list)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::fixture::doc;
+	use alloc::string::ToString;
+
+	fn merge(base: &str, ours: &str, theirs: &str) -> (String, Vec<String>) {
+		let [base, ours, theirs] = [base, ours, theirs].map(doc);
+		let merged = super::merge(&base, &ours, &theirs);
+		let text = merged.document.to_string();
+		assert_eq!(doc(&text), merged.document);
+		(text, merged.conflicts)
+	}
+
+	#[test]
+	fn clean() {
+		let (text, conflicts) = merge(
+			"(pci (1000 net) (1001 blk)) (modules a)",
+			"(pci (1000 net-legacy) (1001 blk)) (modules a)",
+			"(modules a b) (pci (1000 net) (1040 gpu)) (debug)",
+		);
+		assert!(conflicts.is_empty());
+		assert_eq!(
+			text,
+			"(pci (1000 net-legacy) (1040 gpu))\n(modules a b)\n(debug)\n"
+		);
+
+		let (text, conflicts) = merge("(a 1)", "(a 2) (b)", "(a 2) (b)");
+		assert!(conflicts.is_empty());
+		assert_eq!(text, "(a 2)\n(b)\n");
+	}
+
+	#[test]
+	fn conflicts() {
+		let (text, conflicts) = merge(
+			"(pci (1000 net) (1001 blk))",
+			"(pci (1000 net-legacy))",
+			"(pci (1000 net) (1001 blk-mq))",
+		);
+		assert_eq!(conflicts, ["pci/1001"]);
+		assert_eq!(
+			text,
+			"(pci (1000 net-legacy) (!conflict (base (1001 blk)) (ours) (theirs (1001 blk-mq))))\n"
+		);
+
+		let (text, conflicts) = merge("top (a 1) (a 2)", "top (a 1) (a 3)", "(a 1) (a 4)");
+		assert_eq!(conflicts, ["a[1]"]);
+		assert_eq!(
+			text,
+			"(a 1)\n(a (!conflict (base 2) (ours 3) (theirs 4)))\n"
+		);
+	}
+
+	#[test]
+	fn added() {
+		let (text, conflicts) = merge("", "(net (dhcp yes) eth0)", "(net eth0 (mtu 9000))");
+		assert!(conflicts.is_empty());
+		assert_eq!(text, "(net (dhcp yes) eth0 (mtu 9000))\n");
+
+		let (text, conflicts) = merge("", "(net eth0)", "(net eth1)");
+		assert_eq!(conflicts, ["net"]);
+		assert_eq!(text, "(net (!conflict (base) (ours eth0) (theirs eth1)))\n");
+	}
+}
@@ -0,0 +1,619 @@
+use crate::{Groups, GroupsIter, Item, KeyPath, LineCol, Quote, Span};
+use alloc::{borrow::Cow, sync::Arc, vec::Vec};
+use core::{fmt, ops};
+
+/// The file and position a value was read from.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Origin {
+	pub file: Arc<str>,
+	/// The line and column of the start of the value.
+	pub line_col: LineCol,
+}
+
+impl fmt::Display for Origin {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}:{}", self.file, self.line_col)
+	}
+}
+
+/// A symbol or string with escape sequences decoded.
+#[derive(Clone, Debug)]
+pub struct Text<'a> {
+	pub text: Cow<'a, str>,
+	/// The kind of quotes around the string, or `None` for a bare symbol.
+	pub quote: Option<Quote>,
+	pub span: Span,
+	/// Where the text was read from, if known. See [`Document::set_origin`].
+	pub origin: Option<Origin>,
+}
+
+impl<'a> Text<'a> {
+	pub fn new(text: impl Into<Cow<'a, str>>) -> Self {
+		Self {
+			text: text.into(),
+			quote: None,
+			span: Span::default(),
+			origin: None,
+		}
+	}
+
+	pub fn quoted(text: impl Into<Cow<'a, str>>) -> Self {
+		Self {
+			quote: Some(Quote::Double),
+			..Self::new(text)
+		}
+	}
+
+	pub fn as_str(&self) -> &str {
+		&self.text
+	}
+
+	pub fn into_owned(self) -> Text<'static> {
+		Text {
+			text: self.text.into_owned().into(),
+			quote: self.quote,
+			span: self.span,
+			origin: self.origin,
+		}
+	}
+}
+
+/// Texts are equal if they have the same contents and are either both quoted or both bare.
+/// The kind of quotes, the span and the origin are ignored.
+impl PartialEq for Text<'_> {
+	fn eq(&self, other: &Self) -> bool {
+		self.text == other.text && self.quote.is_some() == other.quote.is_some()
+	}
+}
+
+impl Eq for Text<'_> {}
+
+impl fmt::Display for Text<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.quote {
+			Some(_) => crate::write_quoted(f, &self.text),
+			None => crate::write_atom(f, &self.text),
+		}
+	}
+}
+
+/// A group of values.
+///
+/// Equality ignores spans and origins.
+#[derive(Clone, Debug, Default)]
+pub struct List<'a> {
+	pub items: Vec<Value<'a>>,
+	pub span: Span,
+	/// Where the list was read from, if known. See [`Document::set_origin`].
+	pub origin: Option<Origin>,
+}
+
+impl<'a> List<'a> {
+	pub fn new(items: Vec<Value<'a>>) -> Self {
+		Self {
+			items,
+			span: Span::default(),
+			origin: None,
+		}
+	}
+
+	fn from_groups(it: &mut GroupsIter<'a, '_>) -> Self {
+		let items = it.map(Value::from).collect();
+		Self {
+			items,
+			span: it.span(),
+			origin: None,
+		}
+	}
+
+	/// The first item of this list if it is a symbol or string.
+	pub fn head(&self) -> Option<&str> {
+		self.items.first().and_then(Value::as_str)
+	}
+
+	/// All items except the head.
+	pub fn tail(&self) -> &[Value<'a>] {
+		self.items.get(1..).unwrap_or_default()
+	}
+
+	/// The first list in this list with the given head.
+	pub fn get(&self, head: &str) -> Option<&List<'a>> {
+		self.items
+			.iter()
+			.filter_map(Value::as_list)
+			.find(|l| l.head() == Some(head))
+	}
+
+	pub fn get_mut(&mut self, head: &str) -> Option<&mut List<'a>> {
+		self.items
+			.iter_mut()
+			.filter_map(Value::as_list_mut)
+			.find(|l| l.head() == Some(head))
+	}
+
+	/// All lists in this list with the given head.
+	pub fn get_all<'s>(&'s self, head: &'s str) -> impl Iterator<Item = &'s List<'a>> + 's {
+		self.items
+			.iter()
+			.filter_map(Value::as_list)
+			.filter(move |l| l.head() == Some(head))
+	}
+
+	/// The first list at `path`, searching nested lists by their heads.
+	///
+	/// See [`KeyPath`] for the accepted paths.
+	pub fn lookup<P: KeyPath>(&self, path: P) -> Option<&List<'a>> {
+		let mut list = self;
+		for i in 0.. {
+			let Some(head) = path.segment(i) else {
+				return (i > 0).then_some(list);
+			};
+			list = list.get(head)?;
+		}
+		None
+	}
+
+	pub fn lookup_mut<P: KeyPath>(&mut self, path: P) -> Option<&mut List<'a>> {
+		let mut list = self;
+		for i in 0.. {
+			let Some(head) = path.segment(i) else {
+				return (i > 0).then_some(list);
+			};
+			list = list.get_mut(head)?;
+		}
+		None
+	}
+
+	/// All lists at `path`, in order.
+	///
+	/// Unlike [`List::lookup`] this searches every list with a matching head.
+	pub fn lookup_all<P: KeyPath>(&self, path: P) -> Vec<&List<'a>> {
+		let mut v = Vec::new();
+		if path.segment(0).is_some() {
+			self.lookup_into(&path, 0, &mut v);
+		}
+		v
+	}
+
+	fn lookup_into<'s, P: KeyPath>(&'s self, path: &P, depth: usize, out: &mut Vec<&'s List<'a>>) {
+		let Some(head) = path.segment(depth) else {
+			out.push(self);
+			return;
+		};
+		for l in self.items.iter().filter_map(Value::as_list) {
+			if l.head() == Some(head) {
+				l.lookup_into(path, depth + 1, out);
+			}
+		}
+	}
+
+	pub fn iter(&self) -> core::slice::Iter<'_, Value<'a>> {
+		self.items.iter()
+	}
+
+	pub fn iter_mut(&mut self) -> core::slice::IterMut<'_, Value<'a>> {
+		self.items.iter_mut()
+	}
+
+	pub fn len(&self) -> usize {
+		self.items.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.items.is_empty()
+	}
+
+	pub fn into_owned(self) -> List<'static> {
+		List {
+			items: self.items.into_iter().map(Value::into_owned).collect(),
+			span: self.span,
+			origin: self.origin,
+		}
+	}
+}
+
+impl PartialEq for List<'_> {
+	fn eq(&self, other: &Self) -> bool {
+		self.items == other.items
+	}
+}
+
+impl Eq for List<'_> {}
+
+impl<'a> ops::Index<usize> for List<'a> {
+	type Output = Value<'a>;
+
+	fn index(&self, index: usize) -> &Self::Output {
+		&self.items[index]
+	}
+}
+
+impl ops::IndexMut<usize> for List<'_> {
+	fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+		&mut self.items[index]
+	}
+}
+
+/// Get the first list with the given head.
+///
+/// # Panics
+///
+/// If there is no such list.
+impl<'a> ops::Index<&str> for List<'a> {
+	type Output = List<'a>;
+
+	fn index(&self, head: &str) -> &Self::Output {
+		self.get(head)
+			.unwrap_or_else(|| panic!("no list with head {:?}", head))
+	}
+}
+
+impl<'a> IntoIterator for List<'a> {
+	type Item = Value<'a>;
+	type IntoIter = alloc::vec::IntoIter<Value<'a>>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.items.into_iter()
+	}
+}
+
+impl<'a, 'l> IntoIterator for &'l List<'a> {
+	type Item = &'l Value<'a>;
+	type IntoIter = core::slice::Iter<'l, Value<'a>>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.items.iter()
+	}
+}
+
+impl fmt::Display for List<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("(")?;
+		for (i, v) in self.items.iter().enumerate() {
+			if i > 0 {
+				f.write_str(" ")?;
+			}
+			v.fmt(f)?;
+		}
+		f.write_str(")")
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value<'a> {
+	Atom(Text<'a>),
+	List(List<'a>),
+}
+
+impl<'a> Value<'a> {
+	pub fn span(&self) -> Span {
+		match self {
+			Self::Atom(t) => t.span,
+			Self::List(l) => l.span,
+		}
+	}
+
+	pub fn origin(&self) -> Option<&Origin> {
+		match self {
+			Self::Atom(t) => t.origin.as_ref(),
+			Self::List(l) => l.origin.as_ref(),
+		}
+	}
+
+	pub fn as_str(&self) -> Option<&str> {
+		self.as_text().map(Text::as_str)
+	}
+
+	pub fn as_text(&self) -> Option<&Text<'a>> {
+		match self {
+			Self::Atom(t) => Some(t),
+			_ => None,
+		}
+	}
+
+	pub fn as_list(&self) -> Option<&List<'a>> {
+		match self {
+			Self::List(l) => Some(l),
+			_ => None,
+		}
+	}
+
+	pub fn as_list_mut(&mut self) -> Option<&mut List<'a>> {
+		match self {
+			Self::List(l) => Some(l),
+			_ => None,
+		}
+	}
+
+	/// The head of this value if it is a list with a head. See [`List::head`].
+	pub fn head(&self) -> Option<&str> {
+		self.as_list().and_then(List::head)
+	}
+
+	pub fn is_atom(&self) -> bool {
+		matches!(self, Self::Atom(_))
+	}
+
+	pub fn is_list(&self) -> bool {
+		matches!(self, Self::List(_))
+	}
+
+	pub fn into_owned(self) -> Value<'static> {
+		match self {
+			Self::Atom(t) => Value::Atom(t.into_owned()),
+			Self::List(l) => Value::List(l.into_owned()),
+		}
+	}
+}
+
+impl<'a> From<Item<'a, '_>> for Value<'a> {
+	fn from(item: Item<'a, '_>) -> Self {
+		match item {
+			Item::Str(a) => Self::Atom(Text {
+				// The tokenizer already rejects invalid escape sequences.
+				text: a.unescape().unwrap_or(Cow::Borrowed(a.text)),
+				quote: a.quote,
+				span: a.span,
+				origin: None,
+			}),
+			Item::Group(mut g) => Self::List(List::from_groups(&mut g)),
+		}
+	}
+}
+
+impl<'a> From<&'a str> for Value<'a> {
+	fn from(s: &'a str) -> Self {
+		Self::Atom(Text::new(s))
+	}
+}
+
+impl<'a> From<Text<'a>> for Value<'a> {
+	fn from(t: Text<'a>) -> Self {
+		Self::Atom(t)
+	}
+}
+
+impl<'a> From<List<'a>> for Value<'a> {
+	fn from(l: List<'a>) -> Self {
+		Self::List(l)
+	}
+}
+
+impl<'a> From<Vec<Value<'a>>> for Value<'a> {
+	fn from(items: Vec<Value<'a>>) -> Self {
+		Self::List(List::new(items))
+	}
+}
+
+/// Index into a list.
+///
+/// # Panics
+///
+/// If this is not a list or if the index is out of range.
+impl<'a> ops::Index<usize> for Value<'a> {
+	type Output = Value<'a>;
+
+	fn index(&self, index: usize) -> &Self::Output {
+		&self.as_list().expect("not a list")[index]
+	}
+}
+
+/// Get the first list with the given head.
+///
+/// # Panics
+///
+/// If this is not a list or if there is no such list.
+impl<'a> ops::Index<&str> for Value<'a> {
+	type Output = List<'a>;
+
+	fn index(&self, head: &str) -> &Self::Output {
+		&self.as_list().expect("not a list")[head]
+	}
+}
+
+impl fmt::Display for Value<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Atom(t) => t.fmt(f),
+			Self::List(l) => l.fmt(f),
+		}
+	}
+}
+
+/// The items at the top level of a document.
+///
+/// This dereferences to a [`List`] without a head.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Document<'a> {
+	pub root: List<'a>,
+}
+
+impl<'a> Document<'a> {
+	pub fn parse(data: &'a [u8]) -> Result<Self, crate::Error> {
+		Self::from_groups(crate::parse2(data))
+	}
+
+	pub fn from_groups(mut groups: Groups<'a>) -> Result<Self, crate::Error> {
+		let root = List::from_groups(&mut groups.iter());
+		match groups.into_error() {
+			Some(e) => Err(e),
+			None => Ok(Self { root }),
+		}
+	}
+
+	pub fn into_owned(self) -> Document<'static> {
+		Document {
+			root: self.root.into_owned(),
+		}
+	}
+
+	/// Record that all values were parsed from `data`, which is the contents of `file`.
+	///
+	/// The origins are kept when values are moved to other documents,
+	/// such as by [`include`](crate::include) or [`merge`](crate::merge).
+	pub fn set_origin(&mut self, file: &str, data: &[u8]) {
+		let mut loc = Locator {
+			file: file.into(),
+			data,
+			offset: 0,
+			line_col: LineCol { line: 1, column: 1 },
+		};
+		loc.list(&mut self.root);
+	}
+}
+
+/// Finds the origins of values in document order.
+struct Locator<'d> {
+	file: Arc<str>,
+	data: &'d [u8],
+	offset: usize,
+	line_col: LineCol,
+}
+
+impl Locator<'_> {
+	fn at(&mut self, span: Span) -> Option<Origin> {
+		if span.start < self.offset {
+			self.offset = 0;
+			self.line_col = LineCol { line: 1, column: 1 };
+		}
+		let end = span.start.min(self.data.len());
+		self.line_col = crate::advance(self.line_col, &self.data[self.offset.min(end)..end]);
+		self.offset = end;
+		Some(Origin {
+			file: self.file.clone(),
+			line_col: self.line_col,
+		})
+	}
+
+	fn list(&mut self, l: &mut List<'_>) {
+		l.origin = self.at(l.span);
+		for v in &mut l.items {
+			match v {
+				Value::Atom(t) => t.origin = self.at(t.span),
+				Value::List(l) => self.list(l),
+			}
+		}
+	}
+}
+
+impl<'a> ops::Deref for Document<'a> {
+	type Target = List<'a>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.root
+	}
+}
+
+impl ops::DerefMut for Document<'_> {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.root
+	}
+}
+
+/// Items are separated by newlines.
+impl fmt::Display for Document<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for v in self.root.iter() {
+			writeln!(f, "{}", v)?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use crate::fixture::PCI;
+
+	#[test]
+	fn pci() {
+		let doc = Document::parse(PCI).unwrap();
+		let drivers = &doc["pci-drivers"];
+		assert_eq!(drivers.head(), Some("pci-drivers"));
+		assert_eq!(drivers.tail().len(), 2);
+		assert_eq!(
+			drivers["1af4"]["1001"][1].as_str(),
+			Some("drivers/pci/virtio/blk")
+		);
+		assert_eq!(
+			drivers["8086"].span,
+			Span {
+				start: 134,
+				end: 189
+			}
+		);
+		assert!(drivers.get("ffff").is_none());
+		let ids = drivers["1af4"]
+			.tail()
+			.iter()
+			.map(|v| v[0].as_str().unwrap())
+			.collect::<Vec<_>>();
+		assert_eq!(ids, ["1000", "1001", "1040"]);
+
+		assert_eq!(
+			doc.to_string(),
+			"(pci-drivers (1af4 (1000 \"drivers/pci/virtio/net\") (1001 \"drivers/pci/virtio/blk\") \
+			(1040 \"drivers/pci/virtio/gpu\")) (8086 (1616 \"drivers/pci/intel/hd_graphics\")))\n"
+		);
+		let s = doc.to_string();
+		let doc2 = Document::parse(s.as_bytes()).unwrap();
+		assert_eq!(doc, doc2);
+		assert_ne!(doc2.root.span, doc.root.span);
+
+		let mut doc = doc;
+		doc.set_origin("pci.scf", PCI);
+		let o = doc["pci-drivers"]["8086"]["1616"].items[1]
+			.origin()
+			.unwrap();
+		assert_eq!(o.to_string(), "pci.scf:7:9");
+		assert_eq!(doc, doc2);
+	}
+
+	#[test]
+	fn equality() {
+		let doc = Document::parse(br#"(a "a" 'a' "\x61" "b c")"#).unwrap();
+		let l = &doc[0];
+		assert_ne!(l[0], l[1]);
+		assert_eq!(l[1], l[2]);
+		assert_eq!(l[2], l[3]);
+		assert_eq!(l[0], Value::from("a"));
+		assert_eq!(l[4], Value::Atom(Text::quoted("b c")));
+		assert_eq!(doc.to_string(), "(a \"a\" \"a\" \"a\" \"b c\")\n");
+
+		let owned = doc.clone().into_owned();
+		assert_eq!(owned, doc);
+		assert_eq!(
+			Value::from(vec!["x".into(), "y z".into()]).to_string(),
+			"(x \"y z\")"
+		);
+	}
+
+	#[test]
+	fn error() {
+		let e = Document::parse(b"(a (b)").unwrap_err();
+		assert_eq!(e.kind, crate::ErrorKind::UnclosedGroup);
+	}
+
+	#[test]
+	fn lookup() {
+		let mut doc = Document::parse(PCI).unwrap();
+		let l = doc.lookup("pci-drivers/1af4/1040").unwrap();
+		assert_eq!(l.tail()[0].as_str(), Some("drivers/pci/virtio/gpu"));
+		assert!(doc.lookup(["pci-drivers", "8086", "ffff"]).is_none());
+		assert!(doc.lookup("").is_none());
+		assert_eq!(doc.lookup_all("pci-drivers/1af4/*").len(), 0);
+		assert_eq!(doc.lookup_all("pci-drivers/1af4").len(), 1);
+		let l = doc.lookup_mut("pci-drivers/8086/1616").unwrap();
+		l.items[1] = "i915".into();
+		assert_eq!(doc["pci-drivers"]["8086"]["1616"][1].as_str(), Some("i915"));
+
+		let doc = Document::parse(b"(a (b 1)) (a (b 2) (b 3)) (a (c 4))").unwrap();
+		let all = doc.lookup_all("a/b");
+		let all = all
+			.iter()
+			.map(|l| l[1].as_str().unwrap())
+			.collect::<Vec<_>>();
+		assert_eq!(all, ["1", "2", "3"]);
+	}
+}
@@ -0,0 +1,205 @@
+use core::fmt;
+
+/// Write `s` as a bare symbol if possible, otherwise as a double-quoted string.
+///
+/// Parsing the output gives back `s`, after decoding escape sequences.
+pub fn write_atom<W: fmt::Write>(out: &mut W, s: &str) -> fmt::Result {
+	if crate::is_symbol(s) {
+		out.write_str(s)
+	} else {
+		write_quoted(out, s)
+	}
+}
+
+/// Write `s` as a double-quoted string, escaping characters where necessary.
+pub fn write_quoted<W: fmt::Write>(out: &mut W, s: &str) -> fmt::Result {
+	out.write_char('"')?;
+	let mut rest = s;
+	while let Some(i) = rest.find(|c: char| c == '"' || c == '\\' || c.is_ascii_control()) {
+		out.write_str(&rest[..i])?;
+		match rest.as_bytes()[i] {
+			b'"' => out.write_str("\\\"")?,
+			b'\\' => out.write_str("\\\\")?,
+			b'\n' => out.write_str("\\n")?,
+			b'\t' => out.write_str("\\t")?,
+			b'\r' => out.write_str("\\r")?,
+			b'\0' => out.write_str("\\0")?,
+			c => write!(out, "\\x{:02x}", c)?,
+		}
+		rest = &rest[i + 1..];
+	}
+	out.write_str(rest)?;
+	out.write_char('"')
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WriteError {
+	/// The underlying writer failed.
+	Fmt,
+	/// [`Writer::end`] was called without a matching [`Writer::begin`].
+	UnmatchedEnd,
+	/// [`Writer::finish`] was called while groups were still open.
+	Unclosed,
+}
+
+impl From<fmt::Error> for WriteError {
+	fn from(_: fmt::Error) -> Self {
+		Self::Fmt
+	}
+}
+
+impl fmt::Display for WriteError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Fmt => "failed to write",
+			Self::UnmatchedEnd => "closed more groups than were opened",
+			Self::Unclosed => "not all groups were closed",
+		})
+	}
+}
+
+impl core::error::Error for WriteError {}
+
+/// Emits S-expressions to a [`fmt::Write`].
+///
+/// Items in a group are separated by a single space.
+/// Items at the top level are separated by a newline.
+#[derive(Debug)]
+pub struct Writer<W> {
+	out: W,
+	depth: usize,
+	/// Whether the next item needs to be separated from the previous one.
+	separate: bool,
+}
+
+impl<W: fmt::Write> Writer<W> {
+	pub fn new(out: W) -> Self {
+		Self {
+			out,
+			depth: 0,
+			separate: false,
+		}
+	}
+
+	/// The amount of groups that are currently open.
+	pub fn depth(&self) -> usize {
+		self.depth
+	}
+
+	fn separator(&mut self) -> fmt::Result {
+		if self.separate {
+			self.out
+				.write_char(if self.depth == 0 { '\n' } else { ' ' })?;
+		}
+		self.separate = true;
+		Ok(())
+	}
+
+	/// Open a group.
+	pub fn begin(&mut self) -> Result<(), WriteError> {
+		self.separator()?;
+		self.out.write_char('(')?;
+		self.depth += 1;
+		self.separate = false;
+		Ok(())
+	}
+
+	/// Close the innermost open group.
+	pub fn end(&mut self) -> Result<(), WriteError> {
+		if self.depth == 0 {
+			return Err(WriteError::UnmatchedEnd);
+		}
+		self.out.write_char(')')?;
+		self.depth -= 1;
+		self.separate = true;
+		Ok(())
+	}
+
+	/// Write a string, quoting it only if necessary.
+	pub fn atom(&mut self, s: &str) -> Result<(), WriteError> {
+		self.separator()?;
+		write_atom(&mut self.out, s)?;
+		Ok(())
+	}
+
+	/// Write a string, always quoting it.
+	pub fn quoted(&mut self, s: &str) -> Result<(), WriteError> {
+		self.separator()?;
+		write_quoted(&mut self.out, s)?;
+		Ok(())
+	}
+
+	/// Return the underlying writer, checking that all groups were closed.
+	pub fn finish(self) -> Result<W, WriteError> {
+		if self.depth != 0 {
+			return Err(WriteError::Unclosed);
+		}
+		Ok(self.out)
+	}
+
+	/// Return the underlying writer without any checks.
+	pub fn into_inner(self) -> W {
+		self.out
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn roundtrip() {
+		let atoms = [
+			"1af4",
+			"drivers/pci/intel/hd graphics",
+			"",
+			"a\"b'c\\d",
+			"(x)",
+			";",
+			"\n\t\r\0\x07\x7f",
+			"çé😀",
+		];
+		let mut w = Writer::new(String::new());
+		w.begin().unwrap();
+		w.atom("pci-drivers").unwrap();
+		w.begin().unwrap();
+		for a in atoms {
+			w.atom(a).unwrap();
+		}
+		w.end().unwrap();
+		w.quoted("1000").unwrap();
+		w.end().unwrap();
+		w.atom("top").unwrap();
+		assert_eq!(w.end(), Err(WriteError::UnmatchedEnd));
+		let s = w.finish().unwrap();
+		assert_eq!(
+			s,
+			r#"(pci-drivers (1af4 "drivers/pci/intel/hd graphics" "" "a\"b'c\\d" "(x)" ";" "\n\t\r\0\x07\x7f" çé😀) "1000")
+top"#
+		);
+
+		let mut cf = crate::parse2(s.as_bytes());
+		{
+			let mut it = cf.iter();
+			let mut g = it.next_group().unwrap();
+			assert_eq!(g.next_str(), Some("pci-drivers"));
+			let mut g2 = g.next_group().unwrap();
+			for a in atoms {
+				let mut buf = [0; 64];
+				let atom = g2.next().unwrap().into_atom().unwrap();
+				assert_eq!(atom.unescape_into(&mut buf).unwrap(), a);
+			}
+			assert!(g2.next().is_none());
+			drop(g2);
+			assert!(g.next().unwrap().into_atom().unwrap().is_quoted());
+			assert!(g.next().is_none());
+			assert_eq!(it.next_str(), Some("top"));
+			assert!(it.next().is_none());
+		}
+		assert!(cf.into_error().is_none());
+
+		let mut w = Writer::new(String::new());
+		w.begin().unwrap();
+		assert_eq!(w.finish().unwrap_err(), WriteError::Unclosed);
+	}
+}